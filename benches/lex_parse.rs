@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use interpreterbook::{
+    ast::Node,
+    lexer::{Lexer, Token},
+    parser::Parser,
+};
+
+const STATEMENTS: usize = 10_000;
+
+/// Builds the same program on every run, so results are comparable.
+fn program() -> String {
+    let mut source = String::new();
+    for i in 0..STATEMENTS {
+        let line = match i % 4 {
+            0 => format!("let x = {} * (y + {}) ** 2;\n", i, i % 7),
+            1 => format!("a < {}.5 ? b ?? c : -d / {};\n", i, i + 1),
+            2 => format!("match x {{ {} => a && b, _ => {{ !c || d }} }};\n", i),
+            _ => format!("try f - {} catch e {{ e; {} }};\n", i, i),
+        };
+        source.push_str(&line);
+    }
+    source
+}
+
+fn lex(source: &str) -> usize {
+    let mut l = Lexer::new(source);
+    let mut count = 0;
+    while l.next_token().unwrap() != Token::Eof {
+        count += 1;
+    }
+    count
+}
+
+fn parse(source: &str) -> Node {
+    Parser::new(Lexer::new(source))
+        .parse_program()
+        .unwrap_or_else(|errors| panic!("{:?}", errors))
+}
+
+fn bench_lexer(c: &mut Criterion) {
+    let source = program();
+
+    let mut group = c.benchmark_group("lexer");
+    group.throughput(Throughput::Elements(lex(&source) as u64));
+    group.bench_function("tokens", |b| b.iter(|| lex(black_box(&source))));
+    group.finish();
+}
+
+fn bench_parser(c: &mut Criterion) {
+    let source = program();
+
+    match parse(&source) {
+        Node::Program(statements) => assert_eq!(statements.len(), STATEMENTS),
+        node => panic!("unexpected node {:?}", node),
+    }
+
+    let mut group = c.benchmark_group("parser");
+    group.throughput(Throughput::Elements(STATEMENTS as u64));
+    group.bench_function("statements", |b| b.iter(|| parse(black_box(&source))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_lexer, bench_parser);
+criterion_main!(benches);
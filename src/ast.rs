@@ -1,25 +1,90 @@
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 
 use crate::lexer::Token;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Statement {
     Let(Token, Token, Option<Box<Expression>>),
     Return(Token, Option<Box<Expression>>),
     Expression(Token, Option<Box<Expression>>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Identifier(Token),
     IntegerLiteral(Token, i64),
+    FloatLiteral(Token, f64),
     Prefix(Token, Option<Box<Expression>>),
     Infix(Option<Box<Expression>>, Token, Option<Box<Expression>>),
+    Ternary(
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+        Option<Box<Expression>>,
+    ),
+    Match(
+        Option<Box<Expression>>,
+        Vec<(Pattern, Option<Box<Expression>>)>,
+    ),
+    Block(Vec<Statement>),
+    Try(Option<Box<Expression>>, Token, Option<Box<Expression>>),
 }
 
-#[derive(Debug)]
+// Float literals are lexed from digits, so their values are never NaN and
+// equality on them is reflexive.
+impl Eq for Expression {}
+
+impl Hash for Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Expression::Identifier(token) => token.hash(state),
+            Expression::IntegerLiteral(token, value) => {
+                token.hash(state);
+                value.hash(state);
+            }
+            // The value is parsed from the token, so the token is enough.
+            Expression::FloatLiteral(token, _) => token.hash(state),
+            Expression::Prefix(token, expr) => {
+                token.hash(state);
+                expr.hash(state);
+            }
+            Expression::Infix(left, op, right) => {
+                left.hash(state);
+                op.hash(state);
+                right.hash(state);
+            }
+            Expression::Ternary(condition, consequence, alternative) => {
+                condition.hash(state);
+                consequence.hash(state);
+                alternative.hash(state);
+            }
+            Expression::Match(scrutinee, arms) => {
+                scrutinee.hash(state);
+                arms.hash(state);
+            }
+            Expression::Block(statements) => statements.hash(state),
+            Expression::Try(body, binding, handler) => {
+                body.hash(state);
+                binding.hash(state);
+                handler.hash(state);
+            }
+        }
+    }
+}
+
+/// The left-hand side of a `match` arm: `_`, or a value compared against
+/// the scrutinee.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    Wildcard,
+    Value(Option<Box<Expression>>),
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Node {
     Program(Vec<Statement>),
+    Expression(Box<Expression>),
 }
 
 impl Display for Expression {
@@ -27,19 +92,90 @@ impl Display for Expression {
         match self {
             Expression::Identifier(token) => write!(f, "{}", token)?,
             Expression::IntegerLiteral(token, _) => write!(f, "{}", token)?,
-            Expression::Prefix(token, expr) => write!(f, "({}{})", token, expr.as_ref().unwrap())?,
-            Expression::Infix(left, op, right) => write!(
+            Expression::FloatLiteral(token, _) => write!(f, "{}", token)?,
+            Expression::Prefix(token, expr) => write!(f, "({}{})", token, Operand(expr))?,
+            Expression::Infix(left, op, right) => {
+                write!(f, "({} {} {})", Operand(left), op, Operand(right))?
+            }
+            Expression::Ternary(condition, consequence, alternative) => write!(
+                f,
+                "({} ? {} : {})",
+                Operand(condition),
+                Operand(consequence),
+                Operand(alternative)
+            )?,
+            Expression::Match(scrutinee, arms) => {
+                write!(f, "match {} {{", Operand(scrutinee))?;
+                for (i, (pattern, body)) in arms.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {} => {}", pattern, Operand(body))?;
+                }
+                write!(f, " }}")?;
+            }
+            Expression::Block(statements) => {
+                write!(f, "{{")?;
+                for (i, stmt) in statements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ";")?;
+                    }
+                    write!(f, " {}", stmt)?;
+                }
+                write!(f, " }}")?;
+            }
+            Expression::Try(body, binding, handler) => write!(
                 f,
-                "({} {} {})",
-                left.as_ref().unwrap(),
-                op,
-                right.as_ref().unwrap()
+                "try {} catch {} {}",
+                Operand(body),
+                binding,
+                Operand(handler)
             )?,
         }
         Ok(())
     }
 }
 
+/// Displays an operand the parser failed to produce as nothing, the way
+/// `Statement` displays a missing value, so that partial trees can still be
+/// printed in error messages.
+struct Operand<'a>(&'a Option<Box<Expression>>);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(expr) => write!(f, "{}", expr),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Value(expr) => write!(f, "{}", Operand(expr)),
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Program(statements) => {
+                for (i, stmt) in statements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", stmt)?;
+                }
+                Ok(())
+            }
+            Node::Expression(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -69,6 +205,8 @@ impl Display for Statement {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use anyhow::{Ok, Result};
 
     use crate::lexer::Token;
@@ -91,4 +229,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_hash_set() {
+        let float = |text: &str| {
+            Some(Box::new(Expression::FloatLiteral(
+                Token::Float(text.to_string()),
+                text.parse().unwrap(),
+            )))
+        };
+        let sum = |right| {
+            Expression::Infix(
+                Some(Box::new(Expression::Identifier(Token::Ident(
+                    String::from("x"),
+                )))),
+                Token::Plus,
+                right,
+            )
+        };
+
+        let mut set = HashSet::new();
+        set.insert(sum(float("1.5")));
+        set.insert(sum(float("1.5")));
+        assert_eq!(set.len(), 1);
+
+        set.insert(sum(float("2.5")));
+        assert_eq!(set.len(), 2);
+    }
 }
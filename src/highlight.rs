@@ -0,0 +1,113 @@
+use crate::lexer::{Lexer, Token};
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[35m";
+const NUMBER: &str = "\x1b[36m";
+const OPERATOR: &str = "\x1b[33m";
+pub const ERROR: &str = "\x1b[31m";
+
+/// Whether colored output should be used: only when writing to a terminal
+/// and the `NO_COLOR` environment variable is unset or empty.
+pub fn use_color(is_terminal: bool, no_color: Option<&str>) -> bool {
+    is_terminal && no_color.is_none_or(str::is_empty)
+}
+
+/// Returns `source` with ANSI colors around keywords, numbers and
+/// operators. Whitespace and anything the lexer rejects are copied through
+/// unchanged.
+pub fn highlight(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    let mut l = Lexer::new(source);
+
+    while let Ok(token) = l.next_token() {
+        if token == Token::Eof {
+            break;
+        }
+
+        let color = match color(&token) {
+            Some(color) => color,
+            None => continue,
+        };
+
+        let span = l.span();
+
+        out.push_str(&source[copied..span.start]);
+        out.push_str(color);
+        out.push_str(&source[span.start..span.end]);
+        out.push_str(RESET);
+        copied = span.end;
+    }
+
+    out.push_str(&source[copied..]);
+    out
+}
+
+pub fn paint(text: &str, color: &str) -> String {
+    format!("{}{}{}", color, text, RESET)
+}
+
+fn color(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::Function
+        | Token::Let
+        | Token::True
+        | Token::False
+        | Token::If
+        | Token::Else
+        | Token::Return
+        | Token::Match
+        | Token::Try
+        | Token::Catch
+        | Token::Reserved(_) => Some(KEYWORD),
+        Token::Int(_) | Token::Float(_) => Some(NUMBER),
+        Token::Assign
+        | Token::Plus
+        | Token::Minus
+        | Token::Bang
+        | Token::Asterisk
+        | Token::Power
+        | Token::Slash
+        | Token::Lt
+        | Token::Gt
+        | Token::Eq
+        | Token::Neq
+        | Token::Coalesce
+        | Token::And
+        | Token::Or
+        | Token::Question
+        | Token::Colon
+        | Token::FatArrow => Some(OPERATOR),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight, use_color};
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("let x =  5 * y;"),
+            "\x1b[35mlet\x1b[0m x \x1b[33m=\x1b[0m  \x1b[36m5\x1b[0m \x1b[33m*\x1b[0m y;"
+        );
+        assert_eq!(highlight("a @ b"), "a @ b");
+        assert_eq!(
+            highlight("a and not b"),
+            "a \x1b[33mand\x1b[0m \x1b[33mnot\x1b[0m b"
+        );
+        assert_eq!(
+            highlight("1 +\n2e"),
+            "\x1b[36m1\x1b[0m \x1b[33m+\x1b[0m\n2e"
+        );
+    }
+
+    #[test]
+    fn test_use_color() {
+        assert!(use_color(true, None));
+        assert!(use_color(true, Some("")));
+        assert!(!use_color(true, Some("1")));
+        assert!(!use_color(false, None));
+    }
+}
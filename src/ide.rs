@@ -0,0 +1,101 @@
+use crate::lexer::{Lexer, Span, Token};
+
+/// Describes the token under the byte `offset` of `source`, e.g.
+/// `identifier: foo` or `operator: +`. Returns `None` over whitespace,
+/// past the end of the source, or once the lexer hits an error.
+pub fn hover(source: &str, offset: usize) -> Option<String> {
+    let mut l = Lexer::new(source);
+
+    loop {
+        let token = l.next_token().ok()?;
+        if token == Token::Eof || l.span().start > offset {
+            return None;
+        }
+        if l.span().contains(offset) {
+            return describe(&token);
+        }
+    }
+}
+
+/// Finds every place the identifier `name` is bound by a `let` or used,
+/// in source order. Shadowing isn't resolved, so every `name` is returned
+/// whichever binding it refers to. Lexing stops at the first error.
+pub fn find_references(source: &str, name: &str) -> Vec<Span> {
+    let mut l = Lexer::new(source);
+    let mut references = vec![];
+
+    while let Ok(token) = l.next_token() {
+        match token {
+            Token::Eof => break,
+            Token::Ident(ident) if ident == name => references.push(l.span()),
+            _ => {}
+        }
+    }
+
+    references
+}
+
+fn describe(token: &Token) -> Option<String> {
+    let kind = match token {
+        Token::Ident(_) => "identifier",
+        Token::Int(_) => "integer literal",
+        Token::Float(_) => "float literal",
+        Token::Function
+        | Token::Let
+        | Token::True
+        | Token::False
+        | Token::If
+        | Token::Else
+        | Token::Return
+        | Token::Match
+        | Token::Try
+        | Token::Catch => "keyword",
+        Token::Reserved(_) => "reserved word",
+        Token::Comma
+        | Token::Semicolon
+        | Token::Lparen
+        | Token::Rparen
+        | Token::Lbrace
+        | Token::Rbrace => "delimiter",
+        Token::Illegal | Token::Eof | Token::Comment(_) => return None,
+        _ => "operator",
+    };
+
+    Some(format!("{}: {}", kind, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_references, hover};
+    use crate::lexer::Span;
+
+    #[test]
+    fn test_hover() {
+        let source = "let foo = 42;\nfoo  ** 2.5";
+
+        assert_eq!(hover(source, 0), Some(String::from("keyword: let")));
+        assert_eq!(hover(source, 5), Some(String::from("identifier: foo")));
+        assert_eq!(hover(source, 10), Some(String::from("integer literal: 42")));
+        assert_eq!(hover(source, 20), Some(String::from("operator: **")));
+        assert_eq!(hover(source, 23), Some(String::from("float literal: 2.5")));
+        assert_eq!(hover(source, 12), Some(String::from("delimiter: ;")));
+        assert_eq!(hover(source, 3), None);
+        assert_eq!(hover(source, 18), None);
+        assert_eq!(hover(source, 100), None);
+    }
+
+    #[test]
+    fn test_find_references() {
+        let source = "let x = 1;\nlet y = x + xs;\nx * y";
+
+        assert_eq!(
+            find_references(source, "x"),
+            vec![
+                Span { start: 4, end: 5 },
+                Span { start: 19, end: 20 },
+                Span { start: 27, end: 28 },
+            ]
+        );
+        assert_eq!(find_references(source, "z"), vec![]);
+    }
+}
@@ -1,4 +1,7 @@
 use core::fmt;
+use std::borrow::Cow;
+
+use anyhow::{anyhow, bail};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone,)]
 pub enum Token {
@@ -6,24 +9,34 @@ pub enum Token {
     Eof,
     Ident(String),
     Int(String),
+    Float(String),
+    Comment(String),
+    Reserved(String),
 
     Assign,
     Plus,
     Minus,
     Bang,
     Asterisk,
+    Power,
     Slash,
     Lt,
     Gt,
     Eq,
     Neq,
+    Coalesce,
+    And,
+    Or,
 
     Comma,
     Semicolon,
+    Colon,
+    Question,
     Lparen,
     Rparen,
     Lbrace,
     Rbrace,
+    FatArrow,
 
     Function,
     Let,
@@ -32,6 +45,9 @@ pub enum Token {
     If,
     Else,
     Return,
+    Match,
+    Try,
+    Catch,
 }
 
 impl fmt::Display for Token {
@@ -41,24 +57,34 @@ impl fmt::Display for Token {
             Token::Eof => "EOF",
             Token::Ident(ident) => ident,
             Token::Int(value) => value,
+            Token::Float(value) => value,
+            Token::Comment(text) => return write!(f, "//{}", text),
+            Token::Reserved(word) => word,
 
             Token::Assign => "=",
             Token::Plus => "+",
             Token::Minus => "-",
             Token::Bang => "!",
             Token::Asterisk => "*",
+            Token::Power => "**",
             Token::Slash => "/",
             Token::Lt => "<",
             Token::Gt => ">",
             Token::Eq => "==",
             Token::Neq => "!=",
+            Token::Coalesce => "??",
+            Token::And => "&&",
+            Token::Or => "||",
 
             Token::Comma => ",",
             Token::Semicolon => ";",
+            Token::Colon => ":",
+            Token::Question => "?",
             Token::Lparen => "(",
             Token::Rparen => ")",
             Token::Lbrace => "{",
             Token::Rbrace => "}",
+            Token::FatArrow => "=>",
 
             Token::Function => "fn",
             Token::Let => "let",
@@ -67,48 +93,383 @@ impl fmt::Display for Token {
             Token::If => "if",
             Token::Else => "else",
             Token::Return => "return",
+            Token::Match => "match",
+            Token::Try => "try",
+            Token::Catch => "catch",
         };
 
         write!(f, "{}", token_str)
     }
 }
-pub struct Lexer {
-    input: Vec<u8>,
+/// A 1-based line and column in the source. Columns count bytes.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// A byte range `start..end` in the source.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+}
+
+/// A token together with the bytes of the source it was lexed from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// A character or literal the lexer couldn't turn into a token.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub message: String,
+    pub position: Position,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+/// A change to a buffer: the bytes in `span` are replaced with `text`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+pub struct Lexer<'a> {
+    input: Cow<'a, str>,
     pos: usize,
     read_pos: usize,
     ch: u8,
+    line: usize,
+    line_start: usize,
+    token_pos: Position,
+    token_span: Span,
+    position: Position,
+    span: Span,
+    peeked: Option<(anyhow::Result<Token>, Position, Span)>,
+    insert_semicolons: bool,
+    lex_comments: bool,
+    paren_depth: usize,
+    at_statement_end: bool,
 }
 
-impl Lexer {
-    pub fn new(input: String) -> Lexer {
+impl<'a> Lexer<'a> {
+    /// Creates a lexer over borrowed (`&str`) or owned (`String`) source.
+    /// Borrowed source is never copied.
+    pub fn new(input: impl Into<Cow<'a, str>>) -> Lexer<'a> {
         let mut l = Lexer {
-            input: input.into_bytes(),
+            input: input.into(),
             pos: 0,
             read_pos: 0,
             ch: 0,
+            line: 1,
+            line_start: 0,
+            token_pos: Position::default(),
+            token_span: Span::default(),
+            position: Position::default(),
+            span: Span::default(),
+            peeked: None,
+            insert_semicolons: false,
+            lex_comments: false,
+            paren_depth: 0,
+            at_statement_end: false,
         };
         l.read_char();
-        return l;
+        l
+    }
+
+    /// When enabled, a newline ends the current statement as if a `;` had
+    /// been written there, provided the line could end a statement: no
+    /// parenthesis is left open and the last token is an identifier, a
+    /// literal, `)`, `}` or `return`. A line ending in an operator or `,`
+    /// continues onto the next line, and so does one followed by a line
+    /// starting with `}`, `else` or `catch`. Off by default.
+    pub fn set_insert_semicolons(&mut self, enabled: bool) {
+        self.insert_semicolons = enabled;
+    }
+
+    /// When enabled, `//` comments are returned as `Token::Comment` holding
+    /// the text after the slashes, e.g. for a documentation extractor. The
+    /// parser doesn't accept comment tokens, so this is off by default and
+    /// comments are skipped like whitespace.
+    pub fn set_lex_comments(&mut self, enabled: bool) {
+        self.lex_comments = enabled;
     }
 
     fn read_char(&mut self) {
+        if self.ch == b'\n' {
+            self.line += 1;
+            self.line_start = self.read_pos;
+        }
         if self.read_pos >= self.input.len() {
             self.ch = 0
         } else {
-            self.ch = self.input[self.read_pos]
+            self.ch = self.input.as_bytes()[self.read_pos]
         }
         self.pos = self.read_pos;
         self.read_pos += 1
     }
 
     pub fn next_token(&mut self) -> anyhow::Result<Token> {
-        self.skip_whitespace();
+        let (token, position, span) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => (self.lex_token(), self.token_pos, self.token_span),
+        };
+
+        self.position = position;
+        self.span = span;
+        token
+    }
+
+    /// The position of the token (or lexer error) most recently returned by
+    /// `next_token`.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The bytes of the source covered by the token most recently returned
+    /// by `next_token`. An inserted `;` covers the newline it stands for.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Lexes the rest of the input, returning every token up to and
+    /// including `Eof`. Illegal characters and malformed literals are left
+    /// out of the tokens and reported as errors instead.
+    pub fn tokenize_all(&mut self) -> (Vec<SpannedToken>, Vec<LexError>) {
+        let mut tokens = vec![];
+        let mut errors = vec![];
+
+        loop {
+            let result = self.next_token();
+            let span = self.span();
+
+            let message = match result {
+                Ok(Token::Illegal) => match self.input.get(span.start..span.end) {
+                    Some(text) => format!("unexpected character '{}'", text),
+                    None => format!(
+                        "unexpected byte 0x{:02x}",
+                        self.input.as_bytes()[span.start]
+                    ),
+                },
+                Ok(token) => {
+                    let eof = token == Token::Eof;
+                    tokens.push(SpannedToken { token, span });
+                    if eof {
+                        return (tokens, errors);
+                    }
+                    continue;
+                }
+                Err(err) => err.to_string(),
+            };
+
+            errors.push(LexError {
+                message,
+                position: self.position(),
+                span,
+            });
+        }
+    }
+
+    /// Lexes the next token without consuming it. The result is cached, so
+    /// the following `next_token` returns it without lexing the input again.
+    pub fn peek_token(&mut self) -> anyhow::Result<&Token> {
+        if self.peeked.is_none() {
+            let token = self.lex_token();
+            self.peeked = Some((token, self.token_pos, self.token_span));
+        }
+
+        match &self.peeked.as_ref().unwrap().0 {
+            Ok(token) => Ok(token),
+            Err(err) => Err(anyhow!("{}", err)),
+        }
+    }
+
+    fn lex_token(&mut self) -> anyhow::Result<Token> {
+        let token = self.read_token()?;
+        self.track(&token);
+        Ok(token)
+    }
+
+    /// Updates the state semicolon insertion depends on after `token`.
+    fn track(&mut self, token: &Token) {
+        match token {
+            Token::Lparen => self.paren_depth += 1,
+            Token::Rparen => self.paren_depth = self.paren_depth.saturating_sub(1),
+            // A trailing comment doesn't change whether the line can end a
+            // statement.
+            Token::Comment(_) => return,
+            _ => {}
+        }
+        self.at_statement_end = matches!(
+            token,
+            Token::Ident(_)
+                | Token::Int(_)
+                | Token::Float(_)
+                | Token::True
+                | Token::False
+                | Token::Rparen
+                | Token::Rbrace
+                | Token::Return
+        );
+    }
+
+    /// Re-lexes a buffer after `edit`, given the tokens of the buffer before
+    /// the edit. `self` must be a fresh lexer over the edited source, with
+    /// the same settings as the one that produced `old_tokens`, which must
+    /// run up to and including `Eof`.
+    ///
+    /// Tokens ending at least two bytes before the edit (the most the lexer
+    /// looks ahead, as in `1.5`) are kept as they are, except for trailing
+    /// `;`s, which may have been inserted based on what follows them, and
+    /// the bytes of a non-ASCII character split across `Illegal` tokens,
+    /// since lexing can only resume on a character boundary. Lexing
+    /// resumes after the last kept token and stops as soon as it produces a
+    /// token matching an old one past the edit in the same lexer state; the
+    /// rest of the old tokens are then reused, shifted by the change in
+    /// length.
+    pub fn relex(
+        &mut self,
+        edit: &TextEdit,
+        old_tokens: &[SpannedToken],
+    ) -> anyhow::Result<Vec<SpannedToken>> {
+        let old_states: Vec<(usize, bool)> = old_tokens
+            .iter()
+            .map(|old| {
+                self.track(&old.token);
+                (self.paren_depth, self.at_statement_end)
+            })
+            .collect();
+
+        let mut kept = old_tokens
+            .iter()
+            .take_while(|old| old.span.end + 1 < edit.span.start && old.token != Token::Eof)
+            .count();
+        while kept > 0
+            && (old_tokens[kept - 1].token == Token::Semicolon
+                || !self.input.is_char_boundary(old_tokens[kept - 1].span.end))
+        {
+            kept -= 1;
+        }
+        let mut tokens = old_tokens[..kept].to_vec();
+
+        (self.paren_depth, self.at_statement_end) = match kept {
+            0 => (0, false),
+            _ => old_states[kept - 1],
+        };
+        self.seek(tokens.last().map_or(0, |last| last.span.end));
+
+        let edit_end = edit.span.start + edit.text.len();
+        let shift = |offset: usize| offset + edit_end - edit.span.end;
+        let mut next_old = kept;
+
+        loop {
+            let token = self.next_token()?;
+            let span = self.span();
+
+            if span.start >= edit_end {
+                while next_old < old_tokens.len()
+                    && (old_tokens[next_old].span.start < edit.span.end
+                        || shift(old_tokens[next_old].span.start) < span.start)
+                {
+                    next_old += 1;
+                }
+
+                if let Some(old) = old_tokens.get(next_old) {
+                    let state = (self.paren_depth, self.at_statement_end);
+                    if old.token == token
+                        && shift(old.span.start) == span.start
+                        && shift(old.span.end) == span.end
+                        && old_states[next_old] == state
+                    {
+                        tokens.extend(old_tokens[next_old..].iter().map(|old| SpannedToken {
+                            token: old.token.clone(),
+                            span: Span {
+                                start: shift(old.span.start),
+                                end: shift(old.span.end),
+                            },
+                        }));
+                        return Ok(tokens);
+                    }
+                }
+            }
+
+            let eof = token == Token::Eof;
+            tokens.push(SpannedToken { token, span });
+            if eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    /// Moves the lexer to byte `offset` of its input.
+    fn seek(&mut self, offset: usize) {
+        let before = &self.input[..offset];
+        self.line = before.matches('\n').count() + 1;
+        self.line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        self.peeked = None;
+        self.ch = 0;
+        self.read_pos = offset;
+        self.read_char();
+    }
+
+    fn read_token(&mut self) -> anyhow::Result<Token> {
+        if let Some((newline, offset)) = self.skip_whitespace() {
+            if self.insert_semicolons
+                && self.paren_depth == 0
+                && self.at_statement_end
+                && self.ch != b'}'
+                && !self.at_continuation()
+            {
+                self.token_pos = newline;
+                self.token_span = Span {
+                    start: offset,
+                    end: offset + 1,
+                };
+                return Ok(Token::Semicolon);
+            }
+        }
+
+        self.token_pos = self.current_position();
+
+        let start = self.pos;
+        let token = self.read_symbol_or_word();
+        self.token_span = Span {
+            start,
+            end: self.pos.min(self.input.len()),
+        };
+        token
+    }
 
+    fn read_symbol_or_word(&mut self) -> anyhow::Result<Token> {
         let token = match self.ch {
             b'=' => {
                 if self.peek_char() == b'=' {
                     self.read_char();
                     Token::Eq
+                } else if self.peek_char() == b'>' {
+                    self.read_char();
+                    Token::FatArrow
                 } else {
                     Token::Assign
                 }
@@ -123,11 +484,49 @@ impl Lexer {
             }
             b'+' => Token::Plus,
             b'-' => Token::Minus,
-            b'/' => Token::Slash,
-            b'*' => Token::Asterisk,
+            b'/' => {
+                if self.peek_char() == b'/' {
+                    return Ok(Token::Comment(self.read_comment()[2..].to_string()));
+                } else {
+                    Token::Slash
+                }
+            }
+            b'*' => {
+                if self.peek_char() == b'*' {
+                    self.read_char();
+                    Token::Power
+                } else {
+                    Token::Asterisk
+                }
+            }
             b'<' => Token::Lt,
             b'>' => Token::Gt,
             b';' => Token::Semicolon,
+            b':' => Token::Colon,
+            b'?' => {
+                if self.peek_char() == b'?' {
+                    self.read_char();
+                    Token::Coalesce
+                } else {
+                    Token::Question
+                }
+            }
+            b'&' => {
+                if self.peek_char() == b'&' {
+                    self.read_char();
+                    Token::And
+                } else {
+                    Token::Illegal
+                }
+            }
+            b'|' => {
+                if self.peek_char() == b'|' {
+                    self.read_char();
+                    Token::Or
+                } else {
+                    Token::Illegal
+                }
+            }
             b'(' => Token::Lparen,
             b')' => Token::Rparen,
             b',' => Token::Comma,
@@ -139,7 +538,7 @@ impl Lexer {
                 if is_letter(self.ch) {
                     return Ok(lookup_ident(self.read_ident()));
                 } else if self.ch.is_ascii_digit() {
-                    return Ok(Token::Int(self.read_number()));
+                    return self.read_number();
                 } else {
                     Token::Illegal
                 }
@@ -148,42 +547,122 @@ impl Lexer {
 
         self.read_char();
 
-        return Ok(token);
+        Ok(token)
+    }
+
+    /// Reads a `//` comment up to, but not including, the end of the line.
+    fn read_comment(&mut self) -> &str {
+        let pos = self.pos;
+        while self.ch != b'\n' && self.ch != 0 {
+            self.read_char()
+        }
+        &self.input[pos..self.pos]
     }
 
-    fn read_ident(&mut self) -> String {
+    fn read_ident(&mut self) -> &str {
         let pos = self.pos;
         while is_letter(self.ch) {
             self.read_char()
         }
-        return String::from_utf8_lossy(&self.input[pos..self.pos]).to_string();
+        &self.input[pos..self.pos]
     }
 
-    fn read_number(&mut self) -> String {
+    /// Reads an integer or a float. A float has a fractional part (`1.5`), an
+    /// exponent (`1e10`, `1.5e-3`) or both. An exponent marker that isn't
+    /// followed by digits, like `1e` or `1e+`, is a lexer error rather than an
+    /// integer followed by an identifier.
+    fn read_number(&mut self) -> anyhow::Result<Token> {
         let pos = self.pos;
+        let mut is_float = false;
+
+        self.read_digits();
+
+        if self.ch == b'.' && self.peek_char().is_ascii_digit() {
+            is_float = true;
+            self.read_char();
+            self.read_digits();
+        }
+
+        if self.ch == b'e' || self.ch == b'E' {
+            is_float = true;
+            self.read_char();
+            if self.ch == b'+' || self.ch == b'-' {
+                self.read_char();
+            }
+            if !self.ch.is_ascii_digit() {
+                bail!("malformed number literal: {}", &self.input[pos..self.pos]);
+            }
+            self.read_digits();
+        }
+
+        let literal = self.input[pos..self.pos].to_string();
+
+        if is_float {
+            Ok(Token::Float(literal))
+        } else {
+            Ok(Token::Int(literal))
+        }
+    }
+
+    fn read_digits(&mut self) {
         while self.ch.is_ascii_digit() {
             self.read_char()
         }
-        return String::from_utf8_lossy(&self.input[pos..self.pos]).to_string();
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.ch.is_ascii_whitespace() {
-            self.read_char()
+    /// Skips whitespace, returning the position of the first newline in it.
+    fn skip_whitespace(&mut self) -> Option<(Position, usize)> {
+        let mut newline = None;
+        loop {
+            if self.ch.is_ascii_whitespace() {
+                if self.ch == b'\n' && newline.is_none() {
+                    newline = Some((self.current_position(), self.pos));
+                }
+                self.read_char()
+            } else if !self.lex_comments && self.ch == b'/' && self.peek_char() == b'/' {
+                self.read_comment();
+            } else {
+                return newline;
+            }
+        }
+    }
+
+    /// Whether the input continues with a keyword that carries on the
+    /// previous expression, like the `else` of `} else {` or the `catch` of
+    /// `} catch e {`.
+    fn at_continuation(&self) -> bool {
+        let rest = &self.input.as_bytes()[self.pos.min(self.input.len())..];
+        ["else", "catch"].iter().any(|word| {
+            rest.starts_with(word.as_bytes())
+                && !rest.get(word.len()).is_some_and(|&c| is_letter(c))
+        })
+    }
+
+    fn current_position(&self) -> Position {
+        Position {
+            line: self.line,
+            col: self.pos - self.line_start + 1,
         }
     }
 
     fn peek_char(&mut self) -> u8 {
         if self.read_pos >= self.input.len() {
-            return 0;
+            0
         } else {
-            return self.input[self.read_pos];
+            self.input.as_bytes()[self.read_pos]
         }
     }
 }
 
-fn lookup_ident(ident: String) -> Token {
-    match &*ident {
+/// Words set aside for features that haven't landed yet. They lex as
+/// `Token::Reserved` rather than identifiers, so that programs using them
+/// as names don't break once they become keywords.
+pub const RESERVED: &[&str] = &["import", "while", "for", "in", "break", "continue"];
+
+/// Maps a word to its keyword token, or to an identifier. `ident` borrows
+/// the source, so only genuine identifiers allocate.
+fn lookup_ident(ident: &str) -> Token {
+    match ident {
         "fn" => Token::Function,
         "let" => Token::Let,
         "true" => Token::True,
@@ -191,17 +670,24 @@ fn lookup_ident(ident: String) -> Token {
         "if" => Token::If,
         "else" => Token::Else,
         "return" => Token::Return,
-        _ => Token::Ident(ident),
+        "match" => Token::Match,
+        "try" => Token::Try,
+        "catch" => Token::Catch,
+        "and" => Token::And,
+        "or" => Token::Or,
+        "not" => Token::Bang,
+        _ if RESERVED.contains(&ident) => Token::Reserved(ident.to_string()),
+        _ => Token::Ident(ident.to_string()),
     }
 }
 
 fn is_letter(ch: u8) -> bool {
-    return b'a' <= ch && ch <= b'z' || b'A' <= ch && ch <= b'Z' || ch == b'_';
+    ch.is_ascii_alphabetic() || ch == b'_'
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Lexer, Token};
+    use super::{LexError, Lexer, Position, Span, SpannedToken, TextEdit, Token};
     use anyhow::Result;
 
     #[test]
@@ -225,6 +711,12 @@ mod tests {
 
             10 == 10;
             10 != 9;
+            a ? b : c;
+            a ?? b;
+            2 ** 10 * 3;
+            match x { _ => 1 }
+            a && b || !c;
+            a and andy or not c;
         ",
         );
         let tests = [
@@ -301,6 +793,43 @@ mod tests {
             Token::Neq,
             Token::Int(String::from("9")),
             Token::Semicolon,
+            Token::Ident(String::from("a")),
+            Token::Question,
+            Token::Ident(String::from("b")),
+            Token::Colon,
+            Token::Ident(String::from("c")),
+            Token::Semicolon,
+            Token::Ident(String::from("a")),
+            Token::Coalesce,
+            Token::Ident(String::from("b")),
+            Token::Semicolon,
+            Token::Int(String::from("2")),
+            Token::Power,
+            Token::Int(String::from("10")),
+            Token::Asterisk,
+            Token::Int(String::from("3")),
+            Token::Semicolon,
+            Token::Match,
+            Token::Ident(String::from("x")),
+            Token::Lbrace,
+            Token::Ident(String::from("_")),
+            Token::FatArrow,
+            Token::Int(String::from("1")),
+            Token::Rbrace,
+            Token::Ident(String::from("a")),
+            Token::And,
+            Token::Ident(String::from("b")),
+            Token::Or,
+            Token::Bang,
+            Token::Ident(String::from("c")),
+            Token::Semicolon,
+            Token::Ident(String::from("a")),
+            Token::And,
+            Token::Ident(String::from("andy")),
+            Token::Or,
+            Token::Bang,
+            Token::Ident(String::from("c")),
+            Token::Semicolon,
             Token::Eof,
         ];
 
@@ -310,6 +839,423 @@ mod tests {
             let tok = l.next_token()?;
             assert_eq!(tok, tt);
         }
-        return Ok(());
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek_token() -> Result<()> {
+        let mut l = Lexer::new("let x = 5;");
+
+        assert_eq!(*l.peek_token()?, Token::Let);
+        assert_eq!(*l.peek_token()?, Token::Let);
+        assert_eq!(l.next_token()?, Token::Let);
+
+        assert_eq!(l.next_token()?, Token::Ident(String::from("x")));
+        assert_eq!(*l.peek_token()?, Token::Assign);
+        assert_eq!(l.next_token()?, Token::Assign);
+        assert_eq!(l.next_token()?, Token::Int(String::from("5")));
+        assert_eq!(l.next_token()?, Token::Semicolon);
+        assert_eq!(*l.peek_token()?, Token::Eof);
+        assert_eq!(l.next_token()?, Token::Eof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek_token_error() {
+        let mut l = Lexer::new("1e");
+
+        assert!(l.peek_token().is_err());
+        let err = l.next_token().unwrap_err();
+        assert_eq!(err.to_string(), "malformed number literal: 1e");
+    }
+
+    #[test]
+    fn test_semicolon_insertion() -> Result<()> {
+        let input = "let x = 5\nx +\n1\n(x\n)\n";
+
+        let mut l = Lexer::new(input);
+        let mut tokens = vec![];
+        loop {
+            let tok = l.next_token()?;
+            if tok == Token::Eof {
+                break;
+            }
+            tokens.push(tok);
+        }
+        assert!(!tokens.contains(&Token::Semicolon));
+
+        let mut l = Lexer::new(input);
+        l.set_insert_semicolons(true);
+        let tests = [
+            Token::Let,
+            Token::Ident(String::from("x")),
+            Token::Assign,
+            Token::Int(String::from("5")),
+            Token::Semicolon,
+            Token::Ident(String::from("x")),
+            Token::Plus,
+            Token::Int(String::from("1")),
+            Token::Semicolon,
+            Token::Lparen,
+            Token::Ident(String::from("x")),
+            Token::Rparen,
+            Token::Semicolon,
+            Token::Eof,
+        ];
+        for tt in tests {
+            assert_eq!(l.next_token()?, tt);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_semicolon_insertion_after_brace() -> Result<()> {
+        let mut l = Lexer::new(
+            "{\na\n}\n-b\n{ a }\nelse { b }\nelsewhere\ntry { a }\ncatch e {\nb\n}\ncatcher",
+        );
+        l.set_insert_semicolons(true);
+        let tests = [
+            Token::Lbrace,
+            Token::Ident(String::from("a")),
+            Token::Rbrace,
+            Token::Semicolon,
+            Token::Minus,
+            Token::Ident(String::from("b")),
+            Token::Semicolon,
+            Token::Lbrace,
+            Token::Ident(String::from("a")),
+            Token::Rbrace,
+            Token::Else,
+            Token::Lbrace,
+            Token::Ident(String::from("b")),
+            Token::Rbrace,
+            Token::Semicolon,
+            Token::Ident(String::from("elsewhere")),
+            Token::Semicolon,
+            Token::Try,
+            Token::Lbrace,
+            Token::Ident(String::from("a")),
+            Token::Rbrace,
+            Token::Catch,
+            Token::Ident(String::from("e")),
+            Token::Lbrace,
+            Token::Ident(String::from("b")),
+            Token::Rbrace,
+            Token::Semicolon,
+            Token::Ident(String::from("catcher")),
+            Token::Eof,
+        ];
+        for tt in tests {
+            assert_eq!(l.next_token()?, tt);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_comments() -> Result<()> {
+        let input = "// doc\nlet x = 5 // five\nx / 2\n";
+
+        let mut l = Lexer::new(input);
+        l.set_insert_semicolons(true);
+        let tests = [
+            Token::Let,
+            Token::Ident(String::from("x")),
+            Token::Assign,
+            Token::Int(String::from("5")),
+            Token::Semicolon,
+            Token::Ident(String::from("x")),
+            Token::Slash,
+            Token::Int(String::from("2")),
+            Token::Semicolon,
+            Token::Eof,
+        ];
+        for tt in tests {
+            assert_eq!(l.next_token()?, tt);
+        }
+
+        let mut l = Lexer::new(input);
+        l.set_insert_semicolons(true);
+        l.set_lex_comments(true);
+        let tests = [
+            Token::Comment(String::from(" doc")),
+            Token::Let,
+            Token::Ident(String::from("x")),
+            Token::Assign,
+            Token::Int(String::from("5")),
+            Token::Comment(String::from(" five")),
+            Token::Semicolon,
+            Token::Ident(String::from("x")),
+            Token::Slash,
+            Token::Int(String::from("2")),
+            Token::Semicolon,
+            Token::Eof,
+        ];
+        for tt in tests {
+            assert_eq!(l.next_token()?, tt);
+        }
+        assert_eq!(Token::Comment(String::from(" five")).to_string(), "// five");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_positions() -> Result<()> {
+        let mut l = Lexer::new("let x = 5;\n  x + 10\n");
+        l.set_insert_semicolons(true);
+
+        let tests = [
+            (Token::Let, 1, 1),
+            (Token::Ident(String::from("x")), 1, 5),
+            (Token::Assign, 1, 7),
+            (Token::Int(String::from("5")), 1, 9),
+            (Token::Semicolon, 1, 10),
+            (Token::Ident(String::from("x")), 2, 3),
+            (Token::Plus, 2, 5),
+            (Token::Int(String::from("10")), 2, 7),
+            (Token::Semicolon, 2, 9),
+            (Token::Eof, 3, 1),
+        ];
+
+        for (token, line, col) in tests {
+            assert_eq!(*l.peek_token()?, token);
+            assert_eq!(l.next_token()?, token);
+            assert_eq!(l.position(), Position { line, col }, "{}", token);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spans() -> Result<()> {
+        let input = "let xy = 5\n  xy ** 10";
+        let mut l = Lexer::new(input);
+        l.set_insert_semicolons(true);
+
+        let tests = [
+            (Token::Let, "let"),
+            (Token::Ident(String::from("xy")), "xy"),
+            (Token::Assign, "="),
+            (Token::Int(String::from("5")), "5"),
+            (Token::Semicolon, "\n"),
+            (Token::Ident(String::from("xy")), "xy"),
+            (Token::Power, "**"),
+            (Token::Int(String::from("10")), "10"),
+            (Token::Eof, ""),
+        ];
+
+        for (token, text) in tests {
+            assert_eq!(*l.peek_token()?, token);
+            assert_eq!(l.next_token()?, token);
+            let span = l.span();
+            assert_eq!(&input[span.start..span.end], text, "{}", token);
+        }
+        assert_eq!(l.span().start, input.len());
+
+        Ok(())
+    }
+
+    fn spanned_tokens(input: &str) -> Result<Vec<SpannedToken>> {
+        let mut l = Lexer::new(input);
+        l.set_insert_semicolons(true);
+
+        let mut tokens = vec![];
+        loop {
+            let token = l.next_token()?;
+            let eof = token == Token::Eof;
+            tokens.push(SpannedToken {
+                token,
+                span: l.span(),
+            });
+            if eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    #[test]
+    fn test_relex() -> Result<()> {
+        let source = "let x = 5\nlet y = (x +\n 1.)\nx ** y\n";
+
+        let edits = [
+            (8, 9, "50"),
+            (6, 7, "=="),
+            (4, 5, "xs"),
+            (21, 22, "-"),
+            (26, 26, "5"),
+            (27, 28, ""),
+            (28, 28, "}"),
+            (33, 34, "1e5"),
+            (0, 0, "x"),
+            (source.len(), source.len(), "z"),
+        ];
+
+        for (start, end, text) in edits {
+            assert_relex(source, start, end, text)?;
+        }
+
+        // Each byte of `é` lexes as its own `Illegal` token, so lexing must
+        // not resume between them.
+        assert_relex("é**||=>", 3, 5, "||")?;
+        assert_relex("é**||=>", 4, 4, "x")?;
+        assert_relex("a é\nb", 5, 6, "c")?;
+
+        Ok(())
+    }
+
+    fn assert_relex(source: &str, start: usize, end: usize, text: &str) -> Result<()> {
+        let old_tokens = spanned_tokens(source)?;
+        let edited = format!("{}{}{}", &source[..start], text, &source[end..]);
+        let edit = TextEdit {
+            span: Span { start, end },
+            text: text.to_string(),
+        };
+
+        let mut l = Lexer::new(edited.as_str());
+        l.set_insert_semicolons(true);
+
+        assert_eq!(
+            l.relex(&edit, &old_tokens)?,
+            spanned_tokens(&edited)?,
+            "{:?}",
+            edited
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keywords() -> Result<()> {
+        let keywords = [
+            ("fn", Token::Function),
+            ("let", Token::Let),
+            ("true", Token::True),
+            ("false", Token::False),
+            ("if", Token::If),
+            ("else", Token::Else),
+            ("return", Token::Return),
+            ("match", Token::Match),
+            ("try", Token::Try),
+            ("catch", Token::Catch),
+            ("and", Token::And),
+            ("or", Token::Or),
+            ("not", Token::Bang),
+        ];
+
+        let reserved = super::RESERVED
+            .iter()
+            .map(|word| (*word, Token::Reserved(word.to_string())));
+
+        for (word, token) in keywords.into_iter().chain(reserved) {
+            assert_eq!(Lexer::new(word).next_token()?, token);
+
+            for near_miss in [
+                format!("{}_", word),
+                format!("_{}", word),
+                word.to_uppercase(),
+            ] {
+                assert_eq!(
+                    Lexer::new(near_miss.as_str()).next_token()?,
+                    Token::Ident(near_miss.clone())
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokenize_all() {
+        let (tokens, errors) = Lexer::new("a @ 1e+ é").tokenize_all();
+
+        assert_eq!(
+            tokens,
+            vec![
+                SpannedToken {
+                    token: Token::Ident(String::from("a")),
+                    span: Span { start: 0, end: 1 },
+                },
+                SpannedToken {
+                    token: Token::Eof,
+                    span: Span { start: 10, end: 10 },
+                },
+            ]
+        );
+        assert_eq!(
+            errors,
+            vec![
+                LexError {
+                    message: String::from("unexpected character '@'"),
+                    position: Position { line: 1, col: 3 },
+                    span: Span { start: 2, end: 3 },
+                },
+                LexError {
+                    message: String::from("malformed number literal: 1e+"),
+                    position: Position { line: 1, col: 5 },
+                    span: Span { start: 4, end: 7 },
+                },
+                LexError {
+                    message: String::from("unexpected byte 0xc3"),
+                    position: Position { line: 1, col: 9 },
+                    span: Span { start: 8, end: 9 },
+                },
+                LexError {
+                    message: String::from("unexpected byte 0xa9"),
+                    position: Position { line: 1, col: 10 },
+                    span: Span { start: 9, end: 10 },
+                },
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "1:3: unexpected character '@'");
+    }
+
+    #[test]
+    fn test_read_ident_slices_input() -> Result<()> {
+        let input = "foobar + 42";
+        let mut l = Lexer::new(input);
+
+        let ident = l.read_ident();
+        assert_eq!(ident, "foobar");
+        assert!(std::ptr::eq(ident.as_ptr(), input.as_ptr()));
+
+        assert_eq!(l.next_token()?, Token::Plus);
+        assert_eq!(l.next_token()?, Token::Int(String::from("42")));
+        assert_eq!(l.next_token()?, Token::Eof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_float_token() -> Result<()> {
+        let tests = [
+            ("2.5", Token::Float(String::from("2.5"))),
+            ("1e10", Token::Float(String::from("1e10"))),
+            ("1.5e-3", Token::Float(String::from("1.5e-3"))),
+            ("3E+2", Token::Float(String::from("3E+2"))),
+            ("42", Token::Int(String::from("42"))),
+        ];
+
+        for (input, expected) in tests {
+            let mut l = Lexer::new(input.to_string());
+            assert_eq!(l.next_token()?, expected);
+            assert_eq!(l.next_token()?, Token::Eof);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_exponent() {
+        for input in ["1e", "1e+;", "2.5E"] {
+            let mut l = Lexer::new(input.to_string());
+            let err = l.next_token().unwrap_err();
+            assert!(
+                err.to_string().starts_with("malformed number literal"),
+                "{}",
+                err
+            );
+        }
     }
 }
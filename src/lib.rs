@@ -0,0 +1,6 @@
+pub mod ast;
+pub mod highlight;
+pub mod ide;
+pub mod lexer;
+pub mod parser;
+pub mod repl;
@@ -1,8 +1,62 @@
-mod lexer;
-mod repl;
-mod ast;
-mod parser;
+use std::{env, process};
+
+use anyhow::{bail, Result};
+use interpreterbook::{parser::parse_file, repl};
 
 fn main() {
-    repl::start().unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.as_slice() {
+        [] => repl::start().unwrap(),
+        [path] => {
+            if let Err(err) = run_file(path) {
+                eprintln!("{:#}", err);
+                process::exit(1);
+            }
+        }
+        _ => {
+            eprintln!("usage: intpbk [script]");
+            process::exit(2);
+        }
+    }
+}
+
+/// Reads and parses the script at `path`, failing with every error the
+/// script produced.
+fn run_file(path: &str) -> Result<()> {
+    if let Err(errors) = parse_file(path) {
+        let messages: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+        bail!("{}", messages.join("\n"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use anyhow::Result;
+
+    use super::run_file;
+
+    #[test]
+    fn test_run_file_errors() -> Result<()> {
+        let path = env::temp_dir().join(format!("intpbk_run_{}.mk", std::process::id()));
+        fs::write(&path, "let = 5;")?;
+
+        let result = run_file(path.to_str().unwrap());
+        fs::remove_file(&path)?;
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("expected next token to be Ident"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_file_missing() {
+        let err = run_file("/does/not/exist.mk").unwrap_err();
+        assert!(err.to_string().contains("could not read"));
+    }
 }
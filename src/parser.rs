@@ -1,67 +1,248 @@
+use std::{fmt::Display, fs};
+
 use crate::{
-    ast::{Expression, Node, Statement},
-    lexer::{Lexer, Token},
+    ast::{Expression, Node, Pattern, Statement},
+    lexer::{Lexer, Position, Token},
 };
-use anyhow::Result;
 
-pub struct Parser {
-    lexer: Lexer,
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
     cur_token: Option<Token>,
     peek_token: Option<Token>,
-    errors: Vec<String>,
+    cur_pos: Position,
+    peek_pos: Position,
+    errors: Vec<ParseError>,
+    depth: usize,
+    max_depth: usize,
+    too_deep: bool,
+    lex_failed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: Option<Position>,
+    pub file: Option<String>,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(position) = &self.position {
+            write!(f, "{}:", position)?;
+        }
+        if self.file.is_some() || self.position.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Renders `err` followed by the source line it points at and a `^` under
+/// the offending column, e.g. for printing errors in a terminal. Tabs
+/// before the column are kept so the caret lines up, and any other
+/// character counts as one column.
+pub fn render_error(source: &str, err: &ParseError) -> String {
+    let position = match err.position {
+        Some(position) => position,
+        None => return err.to_string(),
+    };
+
+    let line = source
+        .lines()
+        .nth(position.line.saturating_sub(1))
+        .unwrap_or("");
+    let mut col = position.col.saturating_sub(1).min(line.len());
+    while !line.is_char_boundary(col) {
+        col -= 1;
+    }
+
+    let padding: String = line[..col]
+        .chars()
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    format!("{}\n{}\n{}^", err, line, padding)
+}
+
+/// Reads and parses the file at `path`, naming it in every error so they
+/// render as `path:line:col: message`.
+pub fn parse_file(path: &str) -> Result<Node, Vec<ParseError>> {
+    let source = fs::read_to_string(path).map_err(|err| {
+        vec![ParseError {
+            message: format!("could not read file: {}", err),
+            position: None,
+            file: Some(path.to_string()),
+        }]
+    })?;
+
+    let mut lexer = Lexer::new(source);
+    lexer.set_insert_semicolons(true);
+
+    Parser::new(lexer).parse_program().map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|err| ParseError {
+                file: Some(path.to_string()),
+                ..err
+            })
+            .collect()
+    })
 }
 
+/// How deeply expressions may nest before parsing is abandoned, keeping
+/// hostile input like `((((...` from overflowing the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 // Precedence:
 const LOWEST: usize = 1;
-const EQUALS: usize = 2;
-const LESSGREATER: usize = 3;
-const SUM: usize = 4;
-const PRODUCT: usize = 5;
-const PREFIX: usize = 6;
-const CALL: usize = 7;
+const TERNARY: usize = 2;
+const COALESCE: usize = 3;
+const OR: usize = 4;
+const AND: usize = 5;
+const EQUALS: usize = 6;
+const LESSGREATER: usize = 7;
+const SUM: usize = 8;
+const PRODUCT: usize = 9;
+// `**` binds tighter than a prefix operator, so `-2 ** 2` is `-(2 ** 2)`.
+const PREFIX: usize = 10;
+const POWER: usize = 11;
+const CALL: usize = 12;
 
 fn precedence(token: &Option<Token>) -> usize {
     match token.as_ref().unwrap() {
+        Token::Question => TERNARY,
+        Token::Coalesce => COALESCE,
+        Token::Or => OR,
+        Token::And => AND,
         Token::Eq | Token::Neq => EQUALS,
         Token::Lt | Token::Gt => LESSGREATER,
         Token::Plus | Token::Minus => SUM,
         Token::Slash | Token::Asterisk => PRODUCT,
+        Token::Power => POWER,
         Token::Lparen => CALL,
         _ => LOWEST,
     }
 }
 
-impl Parser {
-    pub fn new(lexer: Lexer) -> Self {
+impl<'a> Parser<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
         let mut parser = Parser {
             lexer,
             cur_token: None,
             peek_token: None,
+            cur_pos: Position::default(),
+            peek_pos: Position::default(),
             errors: vec![],
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            too_deep: false,
+            lex_failed: false,
         };
 
         parser.next_token();
         parser.next_token();
 
-        return parser;
+        parser
+    }
+
+    /// Sets how deeply expressions may nest; see `DEFAULT_MAX_DEPTH`.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
     }
 
-    pub fn parse_program(&mut self) -> Result<Node> {
+    /// Parses the whole input. Parsing continues past errors so that every
+    /// error in the input is reported, but any error makes the result `Err`.
+    pub fn parse_program(&mut self) -> Result<Node, Vec<ParseError>> {
         let mut statements: Vec<Statement> = Vec::new();
+        let mut errors = Vec::new();
 
-        while self.cur_token != Some(Token::Eof) {
-            if let Some(stmt) = self.parse_stmt() {
-                statements.push(stmt)
+        for result in self.statements() {
+            match result {
+                Ok(stmt) => statements.push(stmt),
+                Err(err) => errors.push(err),
             }
-            self.next_token();
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
         }
 
         Ok(Node::Program(statements))
     }
 
+    /// Parses statements one at a time as the iterator is advanced, so a
+    /// caller can handle each and drop it before the next is parsed. A
+    /// statement with errors is replaced by its errors, one item each.
+    pub fn statements(
+        &mut self,
+    ) -> impl Iterator<Item = Result<Statement, ParseError>> + use<'_, 'a> {
+        std::iter::from_fn(move || loop {
+            if !self.errors.is_empty() {
+                return Some(Err(self.errors.remove(0)));
+            }
+            if self.cur_token == Some(Token::Eof) {
+                return None;
+            }
+
+            let stmt = self.parse_stmt();
+            self.next_token();
+
+            if let (Some(stmt), true) = (stmt, self.errors.is_empty()) {
+                return Some(Ok(stmt));
+            }
+        })
+    }
+
+    /// Parses the input as a single expression, optionally followed by `;`,
+    /// for callers that want one value rather than a program.
+    pub fn parse_expression(&mut self) -> Result<Node, Vec<ParseError>> {
+        let expr = self.parse_expr(LOWEST);
+
+        if self.peek_token == Some(Token::Semicolon) {
+            self.next_token();
+        }
+
+        if self.peek_token != Some(Token::Eof) {
+            self.error(
+                self.peek_pos,
+                format!(
+                    "expected end of expression, got {}",
+                    self.peek_token.as_ref().unwrap()
+                ),
+            );
+        }
+
+        match expr {
+            Some(expr) if self.errors.is_empty() => Ok(Node::Expression(expr)),
+            _ => Err(std::mem::take(&mut self.errors)),
+        }
+    }
+
+    /// Advances one token. A lexer error is recorded and ends the input: the
+    /// parser sees `Eof` from then on rather than tokens lexed past the error.
     fn next_token(&mut self) {
         self.cur_token = self.peek_token.take();
-        self.peek_token = Some(self.lexer.next_token().expect("Where's my token?"));
+        self.cur_pos = self.peek_pos;
+
+        if self.cur_token == Some(Token::Eof) {
+            self.peek_token = Some(Token::Eof);
+            return;
+        }
+
+        let token = self.lexer.next_token();
+        self.peek_pos = self.lexer.position();
+
+        self.peek_token = match token {
+            Ok(token) => Some(token),
+            Err(err) => {
+                self.error(self.peek_pos, err.to_string());
+                self.lex_failed = true;
+                Some(Token::Eof)
+            }
+        };
     }
 
     fn parse_stmt(&mut self) -> Option<Statement> {
@@ -75,8 +256,16 @@ impl Parser {
     fn parse_let_stmt(&mut self) -> Option<Statement> {
         let let_token = self.cur_token.take().unwrap();
 
+        if let Some(Token::Reserved(word)) = &self.peek_token {
+            let message = reserved_word_error(word);
+            self.error(self.peek_pos, message);
+            self.skip_statement();
+            return None;
+        }
+
         if !matches!(self.peek_token, Some(Token::Ident(_))) {
             self.peek_error(Token::Ident("identifier".to_string()));
+            self.skip_statement();
             return None;
         }
 
@@ -85,15 +274,45 @@ impl Parser {
         let ident_token = self.cur_token.take().unwrap();
 
         if !matches!(self.peek_token, Some(Token::Assign)) {
-            self.peek_error(Token::Assign);
+            self.error(
+                self.peek_pos,
+                format!(
+                    "expected '=', found '{}'",
+                    self.peek_token.as_ref().unwrap()
+                ),
+            );
+            self.skip_statement();
             return None;
         }
 
-        while self.cur_token != Some(Token::Semicolon) {
+        self.next_token();
+        self.next_token();
+
+        let value = self.parse_expr(LOWEST);
+        self.expect_statement_end(value.is_none());
+
+        Some(Statement::Let(let_token, ident_token, value))
+    }
+
+    /// Advances to the end of the current statement, so a malformed
+    /// statement is reported once instead of cascading into more errors.
+    /// Braces are matched up, and the `}` closing an enclosing block is left
+    /// for the block to consume.
+    fn skip_statement(&mut self) {
+        let mut depth: usize = 0;
+        loop {
+            match self.cur_token {
+                Some(Token::Semicolon) if depth == 0 => return,
+                Some(Token::Eof) => return,
+                Some(Token::Lbrace) => depth += 1,
+                Some(Token::Rbrace) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if depth == 0 && self.peek_token == Some(Token::Rbrace) {
+                return;
+            }
             self.next_token();
         }
-
-        Some(Statement::Let(let_token, ident_token, None))
     }
 
     fn peek_error(&mut self, expected: Token) {
@@ -102,42 +321,105 @@ impl Parser {
             expected,
             self.peek_token.as_ref().unwrap()
         );
-        self.errors.push(String::from(msg))
+        self.error(self.peek_pos, msg)
+    }
+
+    fn error(&mut self, position: Position, message: String) {
+        // Everything after hitting the nesting limit is skipped, so any
+        // further errors are just the enclosing expressions failing. The
+        // same goes for a lexer error, which ends the input early.
+        if self.too_deep || self.lex_failed {
+            return;
+        }
+        self.errors.push(ParseError {
+            message,
+            position: Some(position),
+            file: None,
+        })
     }
 
     fn parse_return_stmt(&mut self) -> Option<Statement> {
         let return_token = self.cur_token.take().unwrap();
-        self.next_token();
 
-        while self.cur_token != Some(Token::Semicolon) {
-            self.next_token();
-        }
+        // A bare `return` has no value.
+        let value = match self.peek_token {
+            Some(Token::Semicolon) | Some(Token::Rbrace) | Some(Token::Eof) => None,
+            _ => {
+                self.next_token();
+                self.parse_expr(LOWEST)
+            }
+        };
+        self.expect_statement_end(value.is_none());
 
-        return Some(Statement::Return(return_token, None));
+        Some(Statement::Return(return_token, value))
     }
 
     fn parse_expr_stmt(&mut self) -> Option<Statement> {
         let tok = self.cur_token.clone().unwrap();
 
         let expr = self.parse_expr(LOWEST);
+        self.expect_statement_end(expr.is_none());
 
-        if self.peek_token == Some(Token::Semicolon) {
-            self.next_token();
-        }
+        Some(Statement::Expression(tok, expr))
+    }
 
-        return Some(Statement::Expression(tok, expr));
+    /// Consumes the `;` after a parsed expression. Statements must be
+    /// separated by `;` or a line break, so that `1 2` isn't silently read
+    /// as two statements. The last one in a block may run up to the `}`.
+    fn expect_statement_end(&mut self, failed: bool) {
+        match self.peek_token {
+            Some(Token::Semicolon) => self.next_token(),
+            Some(Token::Eof) | Some(Token::Rbrace) => {}
+            // A failed expression has already been reported.
+            _ if failed || self.peek_pos.line > self.cur_pos.line => {}
+            _ => self.error(
+                self.peek_pos,
+                format!(
+                    "expected ';' or newline after expression, found '{}'",
+                    self.peek_token.as_ref().unwrap()
+                ),
+            ),
+        }
     }
 
     fn parse_expr(&mut self, prec: usize) -> Option<Box<Expression>> {
+        if self.depth >= self.max_depth {
+            self.error(self.cur_pos, String::from("maximum nesting depth exceeded"));
+            self.too_deep = true;
+            while self.cur_token != Some(Token::Eof) {
+                self.next_token();
+            }
+            return None;
+        }
+
+        self.depth += 1;
+        let expr = self.parse_nested_expr(prec);
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_nested_expr(&mut self, prec: usize) -> Option<Box<Expression>> {
+        if let Some(Token::Reserved(word)) = &self.cur_token {
+            let message = reserved_word_error(word);
+            self.error(self.cur_pos, message);
+            return None;
+        }
+
         if !is_prefix_op(self.cur_token.as_ref().unwrap()) {
-            self.errors.push(format!(
-                "no prefix parse function for {}",
-                self.cur_token.as_ref().unwrap()
-            ));
+            self.error(
+                self.cur_pos,
+                format!(
+                    "no prefix parse function for {}",
+                    self.cur_token.as_ref().unwrap()
+                ),
+            );
             return None;
         }
 
         let mut left = self.parse_prefix();
+        // Whether `left` is a `<`/`>` built by this loop, as opposed to one
+        // the prefix parse returned, so only unparenthesized chains count.
+        let mut left_is_comparison = false;
 
         while self.peek_token != Some(Token::Semicolon) && prec < precedence(&self.peek_token) {
             if !is_infix_op(self.peek_token.as_ref().unwrap()) {
@@ -146,10 +428,23 @@ impl Parser {
 
             self.next_token();
 
+            let is_comparison = is_comparison_op(self.cur_token.as_ref().unwrap());
+            if is_comparison && left_is_comparison {
+                self.error(
+                    self.cur_pos,
+                    format!(
+                        "comparison operators cannot be chained: {} {} ...; use && to combine comparisons, e.g. (a < b) && (b < c)",
+                        left.as_ref().unwrap(),
+                        self.cur_token.as_ref().unwrap()
+                    ),
+                );
+            }
+            left_is_comparison = is_comparison;
+
             left = self.parse_infix(left);
         }
 
-        return left;
+        left
     }
 
     fn parse_identifier(&self) -> Option<Box<Expression>> {
@@ -158,10 +453,16 @@ impl Parser {
         )))
     }
 
-    fn parse_integer_literal(&self) -> Option<Box<Expression>> {
+    fn parse_integer_literal(&mut self) -> Option<Box<Expression>> {
         let token = self.cur_token.clone();
         if let Token::Int(val) = token.as_ref().unwrap() {
-            let lit: i64 = val.parse().unwrap();
+            let lit: i64 = match val.parse() {
+                Ok(lit) => lit,
+                Err(_) => {
+                    self.error(self.cur_pos, format!("integer literal too large: {}", val));
+                    return None;
+                }
+            };
 
             Some(Box::new(Expression::IntegerLiteral(token.unwrap(), lit)))
         } else {
@@ -169,11 +470,27 @@ impl Parser {
         }
     }
 
+    fn parse_float_literal(&self) -> Option<Box<Expression>> {
+        let token = self.cur_token.clone();
+        if let Token::Float(val) = token.as_ref().unwrap() {
+            let lit: f64 = val.parse().unwrap();
+
+            Some(Box::new(Expression::FloatLiteral(token.unwrap(), lit)))
+        } else {
+            None
+        }
+    }
+
     fn parse_prefix(&mut self) -> Option<Box<Expression>> {
         match self.cur_token.as_ref() {
             Some(Token::Ident(_)) => self.parse_identifier(),
             Some(Token::Int(_)) => self.parse_integer_literal(),
+            Some(Token::Float(_)) => self.parse_float_literal(),
             Some(Token::Bang) | Some(Token::Minus) => self.parse_prefix_expr(),
+            Some(Token::Lparen) => self.parse_grouped_expr(),
+            Some(Token::Match) => self.parse_match_expr(),
+            Some(Token::Lbrace) => self.parse_block_expr(),
+            Some(Token::Try) => self.parse_try_expr(),
             _ => None,
         }
     }
@@ -184,10 +501,15 @@ impl Parser {
             Some(Token::Minus) => self.parse_infix_expr(left),
             Some(Token::Slash) => self.parse_infix_expr(left),
             Some(Token::Asterisk) => self.parse_infix_expr(left),
+            Some(Token::Power) => self.parse_infix_expr(left),
             Some(Token::Eq) => self.parse_infix_expr(left),
             Some(Token::Neq) => self.parse_infix_expr(left),
             Some(Token::Lt) => self.parse_infix_expr(left),
             Some(Token::Gt) => self.parse_infix_expr(left),
+            Some(Token::Coalesce) => self.parse_infix_expr(left),
+            Some(Token::And) => self.parse_infix_expr(left),
+            Some(Token::Or) => self.parse_infix_expr(left),
+            Some(Token::Question) => self.parse_ternary(left),
             _ => None,
         }
     }
@@ -203,51 +525,224 @@ impl Parser {
     }
 
 
+    fn parse_grouped_expr(&mut self) -> Option<Box<Expression>> {
+        self.next_token();
+
+        let expr = self.parse_expr(LOWEST);
+
+        if !matches!(self.peek_token, Some(Token::Rparen)) {
+            self.peek_error(Token::Rparen);
+            return None;
+        }
+
+        self.next_token();
+
+        expr
+    }
+
+    /// Parses `{ a; b }`, a block of statements used as an expression.
+    fn parse_block_expr(&mut self) -> Option<Box<Expression>> {
+        let mut statements = vec![];
+
+        self.next_token();
+        while self.cur_token != Some(Token::Rbrace) {
+            if self.cur_token == Some(Token::Eof) {
+                self.error(self.cur_pos, String::from("unclosed block, expected '}'"));
+                return None;
+            }
+            if let Some(stmt) = self.parse_stmt() {
+                statements.push(stmt);
+            }
+            self.next_token();
+        }
+
+        Some(Box::new(Expression::Block(statements)))
+    }
+
+    /// Parses `try f catch e { handler }`, where `e` names the error in the
+    /// handler block.
+    fn parse_try_expr(&mut self) -> Option<Box<Expression>> {
+        self.next_token();
+        let body = self.parse_expr(LOWEST);
+
+        if !matches!(self.peek_token, Some(Token::Catch)) {
+            self.peek_error(Token::Catch);
+            return None;
+        }
+        self.next_token();
+
+        if !matches!(self.peek_token, Some(Token::Ident(_))) {
+            self.peek_error(Token::Ident("identifier".to_string()));
+            return None;
+        }
+        self.next_token();
+        let binding = self.cur_token.clone().unwrap();
+
+        if !matches!(self.peek_token, Some(Token::Lbrace)) {
+            self.peek_error(Token::Lbrace);
+            return None;
+        }
+        self.next_token();
+        let handler = self.parse_block_expr();
+
+        Some(Box::new(Expression::Try(body, binding, handler)))
+    }
+
+    /// Parses `match x { 1 => a, _ => b }`. Arms are separated by commas, a
+    /// trailing comma is allowed, and `_` is the wildcard pattern.
+    fn parse_match_expr(&mut self) -> Option<Box<Expression>> {
+        self.next_token();
+        let scrutinee = self.parse_expr(LOWEST);
+
+        if !matches!(self.peek_token, Some(Token::Lbrace)) {
+            self.peek_error(Token::Lbrace);
+            return None;
+        }
+        self.next_token();
+
+        let mut arms = vec![];
+
+        loop {
+            self.next_token();
+            if self.cur_token == Some(Token::Rbrace) {
+                break;
+            }
+
+            let pattern = match self.cur_token.as_ref() {
+                Some(Token::Ident(name)) if name == "_" => Pattern::Wildcard,
+                _ => Pattern::Value(self.parse_expr(LOWEST)),
+            };
+
+            if !matches!(self.peek_token, Some(Token::FatArrow)) {
+                self.peek_error(Token::FatArrow);
+                self.skip_match_arms();
+                return None;
+            }
+            self.next_token();
+            self.next_token();
+
+            arms.push((pattern, self.parse_expr(LOWEST)));
+
+            match self.peek_token {
+                Some(Token::Comma) => self.next_token(),
+                Some(Token::Rbrace) => {
+                    self.next_token();
+                    break;
+                }
+                _ => {
+                    self.peek_error(Token::Rbrace);
+                    self.skip_match_arms();
+                    return None;
+                }
+            }
+        }
+
+        Some(Box::new(Expression::Match(scrutinee, arms)))
+    }
+
+    /// Recovers from a malformed arm by skipping to the `}` that closes the
+    /// match, so the rest of it isn't reported as further errors.
+    fn skip_match_arms(&mut self) {
+        while !matches!(self.cur_token, Some(Token::Rbrace) | Some(Token::Eof)) {
+            self.next_token();
+        }
+    }
+
     fn parse_infix_expr(&mut self, left: Option<Box<Expression>>) -> Option<Box<Expression>> {
         let operator = self.cur_token.take();
-        let precedence = precedence(&operator);
+        let mut precedence = precedence(&operator);
+
+        // `**` is right-associative: parsing the right side one tier lower
+        // lets `2 ** 3 ** 2` group as `2 ** (3 ** 2)`.
+        if operator == Some(Token::Power) {
+            precedence -= 1;
+        }
 
         self.next_token();
         let right = self.parse_expr(precedence);
 
         Some(Box::new(Expression::Infix(left, operator.unwrap(), right)))
     }
+
+    /// Parses `cond ? a : b`. The alternative is parsed one tier below
+    /// `TERNARY` so that `a ? b : c ? d : e` groups as `a ? b : (c ? d : e)`.
+    fn parse_ternary(&mut self, condition: Option<Box<Expression>>) -> Option<Box<Expression>> {
+        self.next_token();
+        let consequence = self.parse_expr(LOWEST);
+
+        if !matches!(self.peek_token, Some(Token::Colon)) {
+            self.peek_error(Token::Colon);
+            return None;
+        }
+
+        self.next_token();
+        self.next_token();
+        let alternative = self.parse_expr(TERNARY - 1);
+
+        Some(Box::new(Expression::Ternary(
+            condition,
+            consequence,
+            alternative,
+        )))
+    }
 }
 
 fn is_prefix_op(token: &Token) -> bool {
-    match token {
-        Token::Ident(_) => true,
-        Token::Int(_) => true,
-        Token::Bang | Token::Minus => true,
-        _ => false,
-    }
+    matches!(
+        token,
+        Token::Ident(_)
+            | Token::Int(_)
+            | Token::Float(_)
+            | Token::Bang
+            | Token::Minus
+            | Token::Lparen
+            | Token::Match
+            | Token::Lbrace
+            | Token::Try
+    )
 }
 
+fn is_comparison_op(token: &Token) -> bool {
+    matches!(token, Token::Lt | Token::Gt)
+}
 
+fn reserved_word_error(word: &str) -> String {
+    format!(
+        "'{}' is a reserved word and cannot be used as an identifier",
+        word
+    )
+}
 
 fn is_infix_op(token: &Token) -> bool {
-    match token {
-        Token::Plus => true,
-        Token::Minus => true,
-        Token::Slash => true,
-        Token::Asterisk => true,
-        Token::Eq => true,
-        Token::Neq => true,
-        Token::Lt => true,
-        Token::Gt => true,
-        _ => false,
-    }
+    matches!(
+        token,
+        Token::Plus
+            | Token::Minus
+            | Token::Slash
+            | Token::Asterisk
+            | Token::Power
+            | Token::Eq
+            | Token::Neq
+            | Token::Lt
+            | Token::Gt
+            | Token::Coalesce
+            | Token::And
+            | Token::Or
+            | Token::Question
+    )
 }
 
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        ast::{Expression, Node, Statement},
+        ast::{Expression, Node, Pattern, Statement},
         lexer::Token,
     };
 
-    use super::{Lexer, Parser};
+    use std::{env, fs};
+
+    use super::{parse_file, render_error, Lexer, ParseError, Parser, Position};
     use anyhow::{Ok, Result};
 
     #[test]
@@ -261,9 +756,9 @@ mod tests {
         assert_eq!(stmts.len(), 3);
 
         let tests = [
-            Token::Ident(String::from("x")),
-            Token::Ident(String::from("y")),
-            Token::Ident(String::from("foobar")),
+            (Token::Ident(String::from("x")), 5),
+            (Token::Ident(String::from("y")), 10),
+            (Token::Ident(String::from("foobar")), 838383),
         ];
 
         for (i, tt) in tests.iter().enumerate() {
@@ -287,11 +782,11 @@ mod tests {
 
         assert_eq!(stmts.len(), 3);
 
-        for stmt in stmts {
+        for (stmt, value) in stmts.into_iter().zip([5, 10, 838383]) {
             match stmt {
                 Statement::Return(token, expr) => {
                     assert_eq!(token, Token::Return);
-                    assert!(expr.is_none());
+                    assert_eq!(expr_to_int(expr.as_ref().unwrap()), value);
                 }
                 _ => panic!("unexpected statement {:?}", stmt),
             }
@@ -300,9 +795,10 @@ mod tests {
         Ok(())
     }
 
-    fn test_let(token: &Token, ident: &Token, _expr: &Option<Box<Expression>>, tt: &Token) {
+    fn test_let(token: &Token, ident: &Token, expr: &Option<Box<Expression>>, tt: &(Token, i64)) {
         assert!(matches!(token, Token::Let), "Expected Let, got {:?}", token);
-        assert_eq!(ident, tt)
+        assert_eq!(*ident, tt.0);
+        assert_eq!(expr_to_int(expr.as_ref().unwrap()), tt.1);
     }
 
     #[test]
@@ -347,6 +843,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_float_literal_expr() -> Result<()> {
+        let tests = [("2.5;", 2.5), ("1e10;", 1e10), ("1.5e-3;", 1.5e-3)];
+
+        for (input, expected) in tests {
+            let stmts = create_program(input);
+            assert_eq!(stmts.len(), 1);
+
+            match &stmts[0] {
+                Statement::Expression(_, expr) => {
+                    if let Expression::FloatLiteral(_, value) = &**expr.as_ref().unwrap() {
+                        assert_eq!(*value, expected);
+                    } else {
+                        panic!("unexpected expression {:?}", expr);
+                    }
+                }
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_prefix_expr() -> Result<()> {
         let stmt = create_program("!5;");
@@ -425,6 +944,18 @@ mod tests {
                 operator: Token::Neq,
                 right: 5,
             },
+            TC {
+                input: "5 ** 5;",
+                left: 5,
+                operator: Token::Power,
+                right: 5,
+            },
+            TC {
+                input: "5 ?? 5;",
+                left: 5,
+                operator: Token::Coalesce,
+                right: 5,
+            },
         ];
 
         for tc in cases {
@@ -468,6 +999,34 @@ mod tests {
                 "3 + 4 * 5 == 3 * 1 + 4 * 5",
                 "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))",
             ),
+            ("a ? b : c", "(a ? b : c)"),
+            ("a == b ? c + 1 : d * 2", "((a == b) ? (c + 1) : (d * 2))"),
+            ("a ? b : c ? d : e", "(a ? b : (c ? d : e))"),
+            ("a ? b ? c : d : e", "(a ? (b ? c : d) : e)"),
+            ("-a ? b : c", "((-a) ? b : c)"),
+            ("a ?? b ?? c", "((a ?? b) ?? c)"),
+            ("a ?? b + 1", "(a ?? (b + 1))"),
+            ("a == b ?? c", "((a == b) ?? c)"),
+            ("a ?? b ? c : d ?? e", "((a ?? b) ? c : (d ?? e))"),
+            ("2 ** 3 ** 2", "(2 ** (3 ** 2))"),
+            ("2 * 3 ** 2", "(2 * (3 ** 2))"),
+            ("a ** b * c", "((a ** b) * c)"),
+            ("-2 ** 2", "(-(2 ** 2))"),
+            ("(-2) ** 2", "((-2) ** 2)"),
+            ("2 ** -3 ** 2", "(2 ** (-(3 ** 2)))"),
+            ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
+            ("(5 + 5) * 2", "((5 + 5) * 2)"),
+            ("2 / (5 + 5)", "(2 / (5 + 5))"),
+            ("-(5 + 5)", "(-(5 + 5))"),
+            ("(a ? b : c) ? d : e", "((a ? b : c) ? d : e)"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b || c", "((a && b) || c)"),
+            ("a == b && c < d", "((a == b) && (c < d))"),
+            ("!a && b", "((!a) && b)"),
+            ("a ?? b || c", "(a ?? (b || c))"),
+            ("a || b ? c : d", "((a || b) ? c : d)"),
+            ("a and b", "(a && b)"),
+            ("a or b and not c", "(a || (b && (!c)))"),
         ];
 
         for t in tests {
@@ -481,8 +1040,550 @@ mod tests {
         Ok(())
     }
 
-    fn expr_to_int(expr: &Box<Expression>) -> i64 {
-        match **expr {
+    #[test]
+    fn test_let_stmt_requires_assign() {
+        let tests = [
+            ("let x == 5;", "expected '=', found '=='"),
+            ("let x 5;", "expected '=', found '5'"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(parse_errors(input), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_parse_program_errors() {
+        let mut parser = Parser::new(Lexer::new("let = 5; let y 3; 1 + 2;"));
+
+        let errors = parser.parse_program().unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError {
+                    message: String::from(
+                        "expected next token to be Ident(\"identifier\"), got Assign instead"
+                    ),
+                    position: Some(Position { line: 1, col: 5 }),
+                    file: None,
+                },
+                ParseError {
+                    message: String::from("expected '=', found '3'"),
+                    position: Some(Position { line: 1, col: 16 }),
+                    file: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expression_statement_separators() {
+        for input in ["1; 2", "1\n2", "1 +\n2\n3"] {
+            let stmts = create_program(input);
+            assert_eq!(stmts.len(), 2, "{:?}", input);
+        }
+
+        let errors = Parser::new(Lexer::new("1 2")).parse_program().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![ParseError {
+                message: String::from("expected ';' or newline after expression, found '2'"),
+                position: Some(Position { line: 1, col: 3 }),
+                file: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        let inputs = [
+            "+",
+            "*",
+            "let",
+            "let x",
+            "let = ;",
+            "(((",
+            ")))",
+            "(1 + )",
+            "1 < 2 < ",
+            "x > < y",
+            "a ? : b",
+            "match",
+            "match x { _ =>",
+            "99999999999999999999",
+            "1e",
+            "@",
+        ];
+
+        for input in inputs {
+            for insert_semicolons in [false, true] {
+                let mut lexer = Lexer::new(input);
+                lexer.set_insert_semicolons(insert_semicolons);
+                let result = Parser::new(lexer).parse_program();
+
+                assert!(result.is_err(), "{:?} parsed as {:?}", input, result);
+            }
+        }
+
+        assert_eq!(
+            parse_errors("99999999999999999999"),
+            vec!["integer literal too large: 99999999999999999999"]
+        );
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let deep = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(parse_errors(&deep), vec!["maximum nesting depth exceeded"]);
+        assert_eq!(
+            parse_errors(&format!("{}1; 2 +", "-".repeat(100_000))),
+            vec!["maximum nesting depth exceeded"]
+        );
+
+        let nested = format!("{}1{}", "(".repeat(10), ")".repeat(10));
+        let mut parser = Parser::new(Lexer::new(nested.as_str()));
+        parser.set_max_depth(11);
+        assert!(parser.parse_program().is_ok());
+
+        let mut parser = Parser::new(Lexer::new(nested.as_str()));
+        parser.set_max_depth(10);
+        let errors = parser.parse_program().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].position, Some(Position { line: 1, col: 11 }));
+    }
+
+    #[test]
+    fn test_block_expr() {
+        let mut lexer = Lexer::new("let x = {\n  let a = 1\n  a + 1\n}\n{ return 2 }\nx");
+        lexer.set_insert_semicolons(true);
+        let program = Parser::new(lexer)
+            .parse_program()
+            .unwrap_or_else(|errors| panic!("{:?}", errors));
+        let stmts = match program {
+            Node::Program(stmts) => stmts,
+            node => panic!("unexpected node {:?}", node),
+        };
+
+        assert_eq!(stmts.len(), 3);
+        assert_eq!(stmts[0].to_string(), "let x = { let a = 1; (a + 1) }");
+        assert_eq!(stmts[1].to_string(), "{ return 2 }");
+        assert_eq!(stmts[2].to_string(), "x");
+
+        let mut lexer = Lexer::new("{ a }\n-b");
+        lexer.set_insert_semicolons(true);
+        let program = Parser::new(lexer).parse_program().unwrap();
+        assert_eq!(program.to_string(), "{ a }; (-b)");
+
+        assert_eq!(parse_errors("{ a; b"), vec!["unclosed block, expected '}'"]);
+        assert_eq!(
+            parse_errors("let x = { let y = "),
+            vec![
+                "no prefix parse function for EOF",
+                "unclosed block, expected '}'"
+            ]
+        );
+        assert_eq!(
+            parse_errors("let x = { a } b"),
+            vec!["expected ';' or newline after expression, found 'b'"]
+        );
+        assert_eq!(
+            parse_errors("{ a b }"),
+            vec!["expected ';' or newline after expression, found 'b'"]
+        );
+        assert_eq!(
+            parse_errors("let x = 5 }"),
+            vec!["no prefix parse function for }"]
+        );
+    }
+
+    #[test]
+    fn test_try_expr() {
+        match parse_expr_node("try a / b catch err { err }") {
+            Node::Expression(expr) => match *expr {
+                Expression::Try(body, binding, handler) => {
+                    assert_eq!(body.unwrap().to_string(), "(a / b)");
+                    assert_eq!(binding, Token::Ident(String::from("err")));
+                    assert_eq!(handler.unwrap().to_string(), "{ err }");
+                }
+                expr => panic!("unexpected expression {:?}", expr),
+            },
+            node => panic!("unexpected node {:?}", node),
+        }
+
+        let mut lexer = Lexer::new("try {\n  a / b\n}\ncatch e {\n  0\n}\nc");
+        lexer.set_insert_semicolons(true);
+        let program = Parser::new(lexer)
+            .parse_program()
+            .unwrap_or_else(|errors| panic!("{:?}", errors));
+        assert_eq!(program.to_string(), "try { (a / b) } catch e { 0 }; c");
+
+        assert_eq!(
+            parse_errors("try a { b }"),
+            vec!["expected next token to be Catch, got Lbrace instead"]
+        );
+        assert_eq!(
+            parse_errors("try a catch { b }"),
+            vec!["expected next token to be Ident(\"identifier\"), got Lbrace instead"]
+        );
+        assert_eq!(
+            parse_errors("try a catch e b"),
+            vec!["expected next token to be Lbrace, got Ident(\"b\") instead"]
+        );
+    }
+
+    #[test]
+    fn test_render_error() {
+        let render = |source: &str| {
+            let errors = Parser::new(Lexer::new(source)).parse_program().unwrap_err();
+            render_error(source, &errors[0])
+        };
+
+        assert_eq!(
+            render("1 + 2;\nlet = 5;"),
+            "2:5: expected next token to be Ident(\"identifier\"), got Assign instead\n\
+             let = 5;\n    \
+             ^"
+        );
+        assert_eq!(
+            render("\tlet x 5;"),
+            "1:8: expected '=', found '5'\n\tlet x 5;\n\t      ^"
+        );
+
+        let error = |position| ParseError {
+            message: String::from("oops"),
+            position,
+            file: None,
+        };
+        let source = "x\né + (";
+        assert_eq!(
+            render_error(source, &error(Some(Position { line: 2, col: 4 }))),
+            "2:4: oops\né + (\n  ^"
+        );
+        assert_eq!(
+            render_error(source, &error(Some(Position { line: 2, col: 2 }))),
+            "2:2: oops\né + (\n^"
+        );
+        assert_eq!(
+            render_error(source, &error(Some(Position { line: 3, col: 1 }))),
+            "3:1: oops\n\n^"
+        );
+        assert_eq!(render_error(source, &error(None)), "oops");
+    }
+
+    #[test]
+    fn test_reserved_words() {
+        assert_eq!(
+            parse_errors("let while = 5;"),
+            vec!["'while' is a reserved word and cannot be used as an identifier"]
+        );
+        assert_eq!(
+            parse_errors("1 + import"),
+            vec!["'import' is a reserved word and cannot be used as an identifier"]
+        );
+        assert_eq!(create_program("let whilst = 5; format").len(), 2);
+    }
+
+    #[test]
+    fn test_statements() {
+        for input in ["let x = 5; x + 1\n-x", "let = 5; 1 2 3; (4", ""] {
+            let program = Parser::new(Lexer::new(input)).parse_program();
+
+            let mut parser = Parser::new(Lexer::new(input));
+            let results: Vec<_> = parser.statements().collect();
+            let (stmts, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+            let stmts: Vec<_> = stmts.into_iter().map(|r| r.unwrap()).collect();
+            let errors: Vec<_> = errors.into_iter().map(|r| r.unwrap_err()).collect();
+
+            match program {
+                std::result::Result::Ok(program) => {
+                    assert!(errors.is_empty(), "{:?}", errors);
+                    assert_eq!(program, Node::Program(stmts));
+                }
+                Err(expected) => assert_eq!(errors, expected),
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::new("1; 2; 3"));
+        let mut statements = parser.statements();
+        assert_eq!(statements.next().unwrap().unwrap().to_string(), "1");
+        assert_eq!(statements.next().unwrap().unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn test_semicolon_insertion() {
+        let mut lexer = Lexer::new("let x = 5\nlet y = 10\nx\n-y\na +\nb");
+        lexer.set_insert_semicolons(true);
+        let newlines = Parser::new(lexer).parse_program().unwrap();
+
+        let semicolons = Parser::new(Lexer::new("let x = 5; let y = 10; x; -y; a + b;"))
+            .parse_program()
+            .unwrap();
+
+        assert_eq!(newlines, semicolons);
+        assert_eq!(
+            newlines.to_string(),
+            "let x = 5; let y = 10; x; (-y); (a + b)"
+        );
+    }
+
+    #[test]
+    fn test_parse_file() -> Result<()> {
+        let path = env::temp_dir().join(format!("intpbk_parse_{}.mk", std::process::id()));
+        fs::write(&path, "let a = 1;\nlet b = 2;\nlet c 3;\n")?;
+
+        let path = path.to_str().unwrap();
+        let result = parse_file(path);
+        fs::remove_file(path)?;
+
+        let errors = result.unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            format!("{}:3:7: expected '=', found '3'", path)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let node = Parser::new(Lexer::new("1 + 2 * x;"))
+            .parse_expression()
+            .unwrap();
+
+        match node {
+            Node::Expression(ref expr) => match **expr {
+                Expression::Infix(ref left, ref op, _) => {
+                    assert_eq!(expr_to_int(left.as_ref().unwrap()), 1);
+                    assert_eq!(*op, Token::Plus);
+                }
+                _ => panic!("unexpected expression {:?}", expr),
+            },
+            _ => panic!("unexpected node {:?}", node),
+        }
+        assert_eq!(node.to_string(), "(1 + (2 * x))");
+
+        let errors = Parser::new(Lexer::new("1 + 2; 3"))
+            .parse_expression()
+            .unwrap_err();
+        assert_eq!(errors[0].message, "expected end of expression, got 3");
+    }
+
+    #[test]
+    fn test_chained_comparison() {
+        assert_eq!(
+            parse_errors("1 < 2 < 3;"),
+            vec!["comparison operators cannot be chained: (1 < 2) < ...; use && to combine comparisons, e.g. (a < b) && (b < c)"]
+        );
+        assert_eq!(
+            parse_errors("a > b + 1 < c"),
+            vec!["comparison operators cannot be chained: (a > (b + 1)) < ...; use && to combine comparisons, e.g. (a < b) && (b < c)"]
+        );
+
+        create_program("5 > 4 == 3 < 4; a < b; c > d; (1 < 2) < 3; (a < b) && (b < c)");
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let corpus = [
+            "a",
+            "42",
+            "2.5",
+            "1.5e-3",
+            "-a",
+            "!a",
+            "!-a",
+            "- -5",
+            "a + b",
+            "a - -b",
+            "a * b / c",
+            "a + b * c - d / e",
+            "a == b != c",
+            "a < b == c > d",
+            "a ?? b ?? c",
+            "2 ** 3 ** 2",
+            "-2 ** 2",
+            "(-2) ** 2",
+            "2 ** -3 ** 2",
+            "(a + b) * c",
+            "a ? b : c ? d : e",
+            "(a ? b : c) ? d : e",
+            "a ?? b ? -c : d ** e",
+            "(1 < 2) < 3",
+            "match x { 1 => a, 2 + 3 => -b, _ => c ? d : e }",
+            "match x {}",
+            "match x { _ => match y { _ => 1 } }",
+            "{ }",
+            "{ a; (b + 1) }",
+            "({ a } * { { b } })",
+            "match { x } { _ => { y } }",
+            "try (a / b) catch e { c }",
+            "(try a catch e { } ?? b)",
+            "try try a catch e { b } catch f { e; f }",
+        ];
+
+        for input in corpus {
+            let first = parse_expr_node(input);
+            let printed = first.to_string();
+            let second = parse_expr_node(&printed);
+
+            assert_eq!(first, second, "{} printed as {}", input, printed);
+        }
+
+        let programs = [
+            "a; b",
+            "x\n-y",
+            "let x = 5; return x; -x",
+            "let y = { let a = 1\n a * 2 }\nreturn y ?? -1",
+            "let z = -2 ** 2; return; z",
+            "{ a }\n-b",
+            "match x { _ => 1 }; a ?? b",
+        ];
+
+        let statements = |node: Node| match node {
+            Node::Program(stmts) => stmts
+                .into_iter()
+                .map(without_first_token)
+                .collect::<Vec<_>>(),
+            node => panic!("unexpected node {:?}", node),
+        };
+
+        for input in programs {
+            let mut lexer = Lexer::new(input);
+            lexer.set_insert_semicolons(true);
+            let first = Parser::new(lexer).parse_program().unwrap();
+            let printed = first.to_string();
+            let second = Parser::new(Lexer::new(printed.as_str()))
+                .parse_program()
+                .unwrap_or_else(|errors| panic!("{}: {:?}", printed, errors));
+
+            assert_eq!(
+                statements(first),
+                statements(second),
+                "{:?} printed as {}",
+                input,
+                printed
+            );
+        }
+    }
+
+    #[test]
+    fn test_grouped_expr_unclosed() {
+        assert_eq!(
+            parse_errors("(1 + 2;"),
+            vec!["expected next token to be Rparen, got Semicolon instead"]
+        );
+    }
+
+    #[test]
+    fn test_match_expr() {
+        let mut lexer = Lexer::new("match x {\n  1 => a,\n  2 => b,\n  _ => c\n}\ny");
+        lexer.set_insert_semicolons(true);
+        let stmts = match Parser::new(lexer).parse_program().unwrap() {
+            Node::Program(stmts) => stmts,
+            node => panic!("unexpected node {:?}", node),
+        };
+        assert_eq!(stmts.len(), 2);
+
+        match &stmts[0] {
+            Statement::Expression(_, expr) => match **expr.as_ref().unwrap() {
+                Expression::Match(ref scrutinee, ref arms) => {
+                    assert_eq!(scrutinee.as_ref().unwrap().to_string(), "x");
+                    assert_eq!(arms.len(), 3);
+                    assert!(matches!(arms[0].0, Pattern::Value(_)));
+                    assert_eq!(arms[0].0.to_string(), "1");
+                    assert_eq!(arms[1].1.as_ref().unwrap().to_string(), "b");
+                    assert_eq!(arms[2].0, Pattern::Wildcard);
+                }
+                _ => panic!("unexpected expression {:?}", expr),
+            },
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
+        let mut lexer = Lexer::new("match x { _ => 1 }\n-2");
+        lexer.set_insert_semicolons(true);
+        let program = Parser::new(lexer).parse_program().unwrap();
+        assert_eq!(program.to_string(), "match x { _ => 1 }; (-2)");
+
+        assert_eq!(
+            parse_errors("match x { 1 a }"),
+            vec!["expected next token to be FatArrow, got Ident(\"a\") instead"]
+        );
+        assert_eq!(
+            parse_errors("match x { 1 => a b }"),
+            vec!["expected next token to be Rbrace, got Ident(\"b\") instead"]
+        );
+    }
+
+    #[test]
+    fn test_lexer_error() {
+        assert_eq!(
+            parse_errors("let x = 1e; let y = 2;"),
+            vec!["malformed number literal: 1e"]
+        );
+
+        let errors = parse_errors("1 + 2e+ 3");
+        assert_eq!(errors[0], "malformed number literal: 2e+");
+    }
+
+    #[test]
+    fn test_ternary_missing_colon() {
+        let errors = parse_errors("a ? b;");
+
+        assert_eq!(
+            errors,
+            vec!["expected next token to be Colon, got Semicolon instead"]
+        );
+    }
+
+    /// Blanks the first token of every expression statement in `stmt`, which
+    /// printing changes from `-` to `(`, so that trees can be compared by
+    /// their expressions alone.
+    fn without_first_token(stmt: Statement) -> Statement {
+        match stmt {
+            Statement::Let(token, ident, value) => {
+                Statement::Let(token, ident, expr_without_first_token(value))
+            }
+            Statement::Return(token, value) => {
+                Statement::Return(token, expr_without_first_token(value))
+            }
+            Statement::Expression(_, value) => {
+                Statement::Expression(Token::Illegal, expr_without_first_token(value))
+            }
+        }
+    }
+
+    fn expr_without_first_token(expr: Option<Box<Expression>>) -> Option<Box<Expression>> {
+        let strip = expr_without_first_token;
+        expr.map(|expr| {
+            Box::new(match *expr {
+                Expression::Prefix(op, right) => Expression::Prefix(op, strip(right)),
+                Expression::Infix(left, op, right) => {
+                    Expression::Infix(strip(left), op, strip(right))
+                }
+                Expression::Ternary(cond, cons, alt) => {
+                    Expression::Ternary(strip(cond), strip(cons), strip(alt))
+                }
+                Expression::Match(scrutinee, arms) => Expression::Match(
+                    strip(scrutinee),
+                    arms.into_iter()
+                        .map(|(pattern, body)| (pattern, strip(body)))
+                        .collect(),
+                ),
+                Expression::Block(stmts) => {
+                    Expression::Block(stmts.into_iter().map(without_first_token).collect())
+                }
+                Expression::Try(body, binding, handler) => {
+                    Expression::Try(strip(body), binding, strip(handler))
+                }
+                expr => expr,
+            })
+        })
+    }
+
+    fn expr_to_int(expr: &Expression) -> i64 {
+        match *expr {
             Expression::IntegerLiteral(_, val) => val,
             _ => panic!(
                 "unexpected expression, expected IntegerLiteral, got {:?}",
@@ -495,16 +1596,31 @@ mod tests {
         let lexer = Lexer::new(input.to_string());
         let mut parser = Parser::new(lexer);
 
-        let program = parser.parse_program().unwrap();
+        let program = parser
+            .parse_program()
+            .unwrap_or_else(|errors| panic!("{:?}", errors));
 
-        for err in parser.errors {
-            panic!("{:?}", err)
+        match program {
+            Node::Program(stmts) => stmts,
+            _ => panic!("unexpected node {:?}", program),
         }
+    }
 
-        let stmts = match program {
-            Node::Program(stmts) => stmts,
-        };
+    fn parse_expr_node(input: &str) -> Node {
+        Parser::new(Lexer::new(input))
+            .parse_expression()
+            .unwrap_or_else(|errors| panic!("{}: {:?}", input, errors))
+    }
+
+    fn parse_errors(input: &str) -> Vec<String> {
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
 
-        return stmts;
+        parser
+            .parse_program()
+            .unwrap_err()
+            .into_iter()
+            .map(|err| err.message)
+            .collect()
     }
 }
@@ -1,33 +1,232 @@
-use std::io::{self, BufRead, Write};
 use anyhow::Result;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::time::Instant;
 
-use crate::{lexer::{self, Token}, parser};
+use crate::{highlight, lexer, parser};
 
 const PROMPT: &str = ">> ";
 
+/// Per-session settings; `color` is fixed at startup, the rest are toggled
+/// with REPL commands.
+struct Options {
+    color: bool,
+    time: bool,
+}
+
 pub fn start() -> Result<()> {
     let stdin = io::stdin();
     let stdout = io::stdout();
 
-    let mut lines = stdin.lock().lines();
-    let mut stdout_lock = stdout.lock();
+    let no_color = env::var("NO_COLOR").ok();
+    let color = highlight::use_color(stdout.is_terminal(), no_color.as_deref());
+
+    run(stdin.lock(), stdout.lock(), color)
+}
+
+/// Runs the REPL over `input`, writing prompts and results to `output`.
+/// With `color`, parsed programs are syntax highlighted and errors are red.
+///
+/// Commands:
+/// - `:load <path>` parses a file as if it had been typed in.
+/// - `:time` toggles printing how long each line took to parse.
+pub fn run<R: BufRead, W: Write>(input: R, mut output: W, color: bool) -> Result<()> {
+    let mut options = Options { color, time: false };
+
+    writeln!(output, "Welcome to the Monkey REPL")?;
+
+    write!(output, "{PROMPT}")?;
+    output.flush()?;
 
+    for line in input.lines() {
+        let line = line?;
+        let command = line.trim();
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
 
-    println!("Welcome to the Monkey REPL");
+        if name == ":load" && argument.is_empty() {
+            writeln!(output, "usage: :load <path>")?;
+        } else if name == ":load" {
+            load(argument.trim(), &mut output, &options)?;
+        } else if command == ":time" {
+            options.time = !options.time;
+            let state = if options.time { "on" } else { "off" };
+            writeln!(output, "timing {}", state)?;
+        } else {
+            parse(line, &mut output, &options)?;
+        }
+
+        write!(output, "{PROMPT}")?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Reads the file at `path` and parses it as if it had been typed into the
+/// prompt. A file that can't be read is reported without ending the session.
+fn load<W: Write>(path: &str, output: &mut W, options: &Options) -> Result<()> {
+    match fs::read_to_string(path) {
+        Ok(source) => parse(source, output, options),
+        Err(err) => {
+            let message = format!("could not load {}: {}", path, err);
+            writeln!(output, "{}", paint_error(&message, options))?;
+            Ok(())
+        }
+    }
+}
 
-    print!("{PROMPT}");
-    stdout_lock.flush()?;
+fn parse<W: Write>(source: String, output: &mut W, options: &Options) -> Result<()> {
+    let start = Instant::now();
 
-    while let Some(line) = lines.next() {
-        let mut l = lexer::Lexer::new(line?);
-        let mut parser = parser::Parser::new(l);
-        parser.parse_program();
+    let mut l = lexer::Lexer::new(source);
+    l.set_insert_semicolons(true);
+    let mut parser = parser::Parser::new(l);
+    let result = parser.parse_program();
 
+    let elapsed = start.elapsed();
 
-        print!("{}", PROMPT);
-        stdout_lock.flush()?;
+    match result {
+        Ok(program) if options.color => {
+            writeln!(output, "{}", highlight::highlight(&program.to_string()))?
+        }
+        Ok(program) => writeln!(output, "{}", program)?,
+        Err(errors) => {
+            for err in errors {
+                writeln!(output, "\t{}", paint_error(&err.to_string(), options))?;
+            }
+        }
+    }
+
+    if options.time {
+        writeln!(output, "(parsed in {:.1?})", elapsed)?;
+    }
+
+    Ok(())
+}
+
+fn paint_error(message: &str, options: &Options) -> String {
+    if options.color {
+        highlight::paint(message, highlight::ERROR)
+    } else {
+        message.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use anyhow::Result;
+
+    use super::run;
+
+    fn run_lines(input: &str) -> Result<String> {
+        run_lines_with_color(input, false)
+    }
 
+    fn run_lines_with_color(input: &str, color: bool) -> Result<String> {
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, color)?;
+        Ok(String::from_utf8(output)?)
     }
 
-    return Ok(())
+    #[test]
+    fn test_time() -> Result<()> {
+        let output = run_lines("1 + 2\n:time\n1 + 2\nlet 5;\n:time\n1 + 2\n")?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[1], ">> (1 + 2)");
+        assert_eq!(lines[2], ">> timing on");
+        assert_eq!(lines[3], ">> (1 + 2)");
+        assert!(lines[4].starts_with("(parsed in "), "{:?}", lines);
+        assert!(lines[4].ends_with(")"), "{:?}", lines);
+        assert!(lines[5].starts_with(">> \t1:5: "), "{:?}", lines);
+        assert!(lines[6].starts_with("(parsed in "), "{:?}", lines);
+        assert_eq!(lines[7], ">> timing off");
+        assert_eq!(lines[8], ">> (1 + 2)");
+        assert_eq!(lines[9], ">> ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_color() -> Result<()> {
+        let input = "let x = 5;\n1 + 2\nlet 5;\n";
+
+        let plain = run_lines(input)?;
+        assert!(!plain.contains('\x1b'), "{:?}", plain);
+        assert!(plain.contains(">> (1 + 2)\n"), "{:?}", plain);
+
+        let colored = run_lines_with_color(input, true)?;
+        assert!(
+            colored.contains("(\x1b[36m1\x1b[0m \x1b[33m+\x1b[0m \x1b[36m2\x1b[0m)"),
+            "{:?}",
+            colored
+        );
+        assert!(colored.contains("\t\x1b[31m1:5: "), "{:?}", colored);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_file() -> Result<()> {
+        let path = env::temp_dir().join(format!("intpbk_load_{}.mk", std::process::id()));
+        fs::write(&path, "let answer = 42;")?;
+
+        let output = run_lines(&format!(":load {}\nanswer\n", path.display()));
+        fs::remove_file(&path)?;
+        let output = output?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[1], ">> let answer = 42");
+        assert_eq!(lines[2], ">> answer");
+        assert_eq!(lines[3], ">> ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_file_with_errors() -> Result<()> {
+        let path = env::temp_dir().join(format!("intpbk_load_errors_{}.mk", std::process::id()));
+        fs::write(&path, "let a = 1\nlet b 2\na + b\n")?;
+
+        let output = run_lines(&format!(":load {}\n1 + 2\n", path.display()));
+        fs::remove_file(&path)?;
+        let output = output?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[1], ">> \t2:7: expected '=', found '2'");
+        assert_eq!(lines[2], ">> (1 + 2)");
+        assert_eq!(lines[3], ">> ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_command_name() -> Result<()> {
+        let output = run_lines(":loadfoo\n:load\n")?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines[1].starts_with(">> \t1:1: "), "{:?}", lines);
+        assert_eq!(lines[2], ">> usage: :load <path>");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_missing_file() -> Result<()> {
+        let output = run_lines(":load /does/not/exist.mk\n1 + 2\n")?;
+
+        assert!(
+            output.contains("could not load /does/not/exist.mk"),
+            "{}",
+            output
+        );
+        assert!(output.contains("(1 + 2)"), "{}", output);
+
+        Ok(())
+    }
 }